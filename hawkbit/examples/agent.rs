@@ -0,0 +1,96 @@
+// Copyright 2025, Liebherr Digital Development Center GmbH.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::path::Path;
+
+use anyhow::Result;
+use hawkbit::ddi::{
+    Agent, Client, ClientAuthorization, ConfirmationInfo, ConfirmationResponse, Error, Update,
+    UpdateHandler,
+};
+use serde::Serialize;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "agent example")]
+struct Opt {
+    url: String,
+    controller: String,
+    key: String,
+    #[structopt(short, long, default_value = "DEFAULT")]
+    tenant: String,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ConfigData {
+    #[serde(rename = "HwRevision")]
+    hw_revision: String,
+}
+
+struct Handler;
+
+impl UpdateHandler for Handler {
+    type Config = ConfigData;
+    type Error = Error;
+
+    async fn on_config_request(&mut self) -> ConfigData {
+        println!("Uploading config data");
+        ConfigData {
+            hw_revision: "1.0".to_string(),
+        }
+    }
+
+    async fn on_confirmation(&mut self, info: &ConfirmationInfo) -> ConfirmationResponse {
+        println!("Confirming update {}", info.action_id());
+        ConfirmationResponse::Confirmed
+    }
+
+    async fn on_update(&mut self, update: &Update) -> Result<(), Error> {
+        println!("Pending update");
+
+        let artifacts = update.download(Path::new("./download/")).await?;
+        dbg!(&artifacts);
+
+        #[cfg(feature = "hash-digest")]
+        for artifact in artifacts {
+            #[cfg(feature = "hash-md5")]
+            artifact.check_md5().await?;
+            #[cfg(feature = "hash-sha1")]
+            artifact.check_sha1().await?;
+            #[cfg(feature = "hash-sha256")]
+            artifact.check_sha256().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn on_cancel(&mut self, action_id: &str) -> bool {
+        println!("Action cancelled: {}", action_id);
+        true
+    }
+
+    fn on_error(&mut self, error: &Error) {
+        eprintln!("Error: {}", error);
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let opt = Opt::from_args();
+
+    let auth = ClientAuthorization::TargetToken(opt.key);
+    let ddi = Client::new(
+        &opt.url,
+        &opt.tenant,
+        &opt.controller,
+        auth,
+        None,
+        None,
+        None,
+    )?;
+
+    let mut agent = Agent::new(ddi, Handler);
+    agent.run().await;
+
+    Ok(())
+}
@@ -84,15 +84,11 @@ async fn main() -> Result<()> {
         }
 
         if let Some(cancel_action) = reply.cancel_action() {
-            println!("Action to cancel: {}", cancel_action.id().await?);
+            let info = cancel_action.fetch().await?;
+            println!("Action to cancel: {}", info.stop_id());
 
-            cancel_action
-                .send_feedback(Execution::Proceeding, Finished::None, vec!["Cancelling"])
-                .await?;
-
-            cancel_action
-                .send_feedback(Execution::Closed, Finished::Success, vec![])
-                .await?;
+            info.report_progress(vec!["Cancelling"]).await?;
+            info.accept(vec![]).await?;
 
             println!("Action cancelled");
         }
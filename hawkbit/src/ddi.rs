@@ -6,30 +6,58 @@
 //! This module provides API for devices to poll their hawkBit server, upload their configuration
 //! and download updates.
 //!
-//! Devices would typically create a [`Client`] using [`Client::new`]
+//! Devices would typically create a [`Client`] using [`Client::new`] or [`Client::builder`]
 //! and would then regularly call [`Client::poll`] checking for updates.
 //!
 //! See `examples/polling.rs` demonstrating how to use it.
+//!
+//! Alternatively, an [`Agent`] can take care of the polling loop and dispatch
+//! the requests from the server to an [`UpdateHandler`], see `examples/agent.rs`.
 
 // FIXME: set link to hawbit/examples/polling.rs once we have the final public repo
 
+mod agent;
+mod auth;
 mod cancel_action;
 mod client;
 mod common;
 mod config_data;
 mod confirmation_base;
 mod deployment_base;
+mod download;
+mod events;
 mod feedback;
+mod feedback_queue;
+mod persist;
 mod poll;
+mod rate_limit;
+mod redirect;
+mod retry;
+mod state;
+mod verification;
 
-pub use cancel_action::CancelAction;
-pub use client::{Client, ClientAuthorization, Error};
+pub use agent::{Agent, PollTrigger, UpdateHandler};
+pub use auth::{AuthProvider, FileToken};
+pub use cancel_action::{CancelAction, CancelInfo};
+pub use client::{Client, ClientAuthorization, ClientBuilder, Endpoint, Error};
 pub use common::{Execution, Finished};
 pub use config_data::{ConfigRequest, Mode};
 pub use confirmation_base::{ConfirmationInfo, ConfirmationRequest, ConfirmationResponse};
-#[cfg(feature = "hash-digest")]
-pub use deployment_base::ChecksumType;
 pub use deployment_base::{
-    Artifact, Chunk, DownloadedArtifact, MaintenanceWindow, Type, Update, UpdatePreFetch,
+    Artifact, Chunk, DownloadedArtifact, Hashes, MaintenanceWindow, Md5Sum, Type, Update,
+    UpdatePreFetch,
 };
-pub use poll::Reply;
+pub use download::{
+    ArtifactPathMapper, DownloadLayout, DownloadObserver, DownloadOptions, DownloadProgress,
+    FilenamePolicy, ProgressFeedback, TransportPreference,
+};
+pub use events::DdiEvent;
+pub use feedback_queue::FeedbackQueue;
+pub use poll::{PollingPolicy, Reply};
+pub use rate_limit::RateLimiter;
+pub use redirect::RedirectPolicy;
+pub use retry::RetryPolicy;
+pub use state::{ActionPhase, ActionState, FileStateStore, StateStore};
+#[cfg(feature = "hash-digest")]
+pub use verification::ChecksumType;
+pub use verification::VerificationPolicy;
@@ -0,0 +1,351 @@
+// Copyright 2025, Liebherr Digital Development Center GmbH.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// High level polling loop dispatching server requests to a handler
+
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::{self, Either};
+use serde::Serialize;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use crate::ddi::cancel_action::{wait_for_cancel, CancelAction, CANNOT_CANCEL};
+use crate::ddi::client::{Client, Error};
+use crate::ddi::common::{Execution, Finished};
+use crate::ddi::confirmation_base::{ConfirmationInfo, ConfirmationRequest, ConfirmationResponse};
+use crate::ddi::deployment_base::Update;
+use crate::ddi::poll::PollingPolicy;
+
+/// Sleeping time used until the server provided its polling interval.
+const DEFAULT_POLLING_SLEEP: Duration = Duration::from_secs(60);
+
+/// Handles the requests received from the server by an [`Agent`].
+///
+/// Implementations can use `async fn` for all the methods.
+pub trait UpdateHandler {
+    /// Device configuration uploaded to the server.
+    type Config: Serialize;
+    /// Error returned when an update failed.
+    type Error: fmt::Display;
+
+    /// The server requested the device configuration.
+    ///
+    /// The returned configuration is uploaded to the server.
+    fn on_config_request(&mut self) -> impl Future<Output = Self::Config> + Send;
+
+    /// The server is waiting for the device to confirm the update described in `info`.
+    fn on_confirmation(
+        &mut self,
+        info: &ConfirmationInfo,
+    ) -> impl Future<Output = ConfirmationResponse> + Send;
+
+    /// The server asked the device to deploy `update`.
+    ///
+    /// The [`Agent`] reports the update as [`Execution::Proceeding`] before calling this method
+    /// and closes it with [`Finished::Success`] or [`Finished::Failure`] depending on the result.
+    /// Additional feedback can be sent using [`Update::send_feedback`].
+    ///
+    /// Meanwhile the agent keeps on polling the server. If the cancellation of the update is
+    /// requested, [`Update::cancellation_token`] is cancelled, interrupting its downloads.
+    /// The cancellation is confirmed to the server if this method then returns an error for
+    /// which [`UpdateHandler::is_interrupted`] returns `true`. Otherwise it is rejected and
+    /// the result of the update is reported as usual.
+    fn on_update(
+        &mut self,
+        update: &Update,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Whether `error`, returned by [`UpdateHandler::on_update`], means the update stopped
+    /// because its [`Update::cancellation_token`] has been cancelled.
+    ///
+    /// The default implementation returns `false`, so the cancellation is rejected and
+    /// the error is reported as a failure of the update.
+    fn is_interrupted(&self, _error: &Self::Error) -> bool {
+        false
+    }
+
+    /// The server asked the device to cancel the action `action_id`.
+    ///
+    /// Return `true` if the action has been cancelled, or `false` if it cannot be cancelled anymore.
+    fn on_cancel(&mut self, action_id: &str) -> impl Future<Output = bool> + Send;
+
+    /// An error occurred while communicating with the server.
+    ///
+    /// The [`Agent`] keeps on polling the server; the default implementation ignores the error.
+    fn on_error(&mut self, _error: &Error) {}
+}
+
+/// Handle waking up an [`Agent`] so it polls the server immediately.
+///
+/// This can be used to react to local events, such as the network coming back
+/// or the user asking to check for updates.
+/// If the agent is busy handling a request, it polls the server again as soon as it is done.
+#[derive(Debug, Clone, Default)]
+pub struct PollTrigger {
+    notify: Arc<Notify>,
+}
+
+impl PollTrigger {
+    /// Wake up the agent so it polls the server without waiting for the end of its polling interval.
+    pub fn poll_now(&self) {
+        self.notify.notify_one();
+    }
+
+    async fn triggered(&self) {
+        self.notify.notified().await
+    }
+}
+
+/// Device agent regularly polling the server and dispatching its requests to an [`UpdateHandler`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use hawkbit::ddi::{Agent, Client, ClientAuthorization, ConfirmationInfo, ConfirmationResponse, Update, UpdateHandler};
+///
+/// struct Handler;
+///
+/// impl UpdateHandler for Handler {
+///     type Config = Vec<(String, String)>;
+///     type Error = std::io::Error;
+///
+///     async fn on_config_request(&mut self) -> Self::Config {
+///         vec![("HwRevision".to_string(), "1.0".to_string())]
+///     }
+///
+///     async fn on_confirmation(&mut self, _info: &ConfirmationInfo) -> ConfirmationResponse {
+///         ConfirmationResponse::Confirmed
+///     }
+///
+///     async fn on_update(&mut self, update: &Update) -> Result<(), Self::Error> {
+///         update.download(std::path::Path::new("./download/")).await.map_err(std::io::Error::other)?;
+///         // install the update
+///         Ok(())
+///     }
+///
+///     async fn on_cancel(&mut self, _action_id: &str) -> bool {
+///         true
+///     }
+/// }
+///
+/// # async fn run() -> Result<(), hawkbit::ddi::Error> {
+/// let auth = ClientAuthorization::TargetToken("secret".to_string());
+/// let client = Client::new("http://my-server.com:8080", "DEFAULT", "my-device", auth, None, None, None)?;
+/// let mut agent = Agent::new(client, Handler);
+/// agent.run().await;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Agent<H> {
+    client: Client,
+    handler: H,
+    polling_sleep: Duration,
+    polling_policy: PollingPolicy,
+    trigger: PollTrigger,
+    cancellation: CancellationToken,
+}
+
+impl<H: UpdateHandler> Agent<H> {
+    /// Create a new agent polling the server using `client`.
+    pub fn new(client: Client, handler: H) -> Self {
+        Self {
+            client,
+            handler,
+            polling_sleep: DEFAULT_POLLING_SLEEP,
+            polling_policy: PollingPolicy::default(),
+            trigger: PollTrigger::default(),
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    /// Set the policy adjusting the polling interval suggested by the server.
+    pub fn polling_policy(self, policy: PollingPolicy) -> Self {
+        let mut agent = self;
+        agent.polling_policy = policy;
+        agent
+    }
+
+    /// Stop the agent when `token` is cancelled.
+    ///
+    /// Unlike the `shutdown` future of [`Agent::run_until`], the update being handled, if any,
+    /// is interrupted using its [`Update::cancellation_token`]. No feedback is then sent about
+    /// it so it is deployed again once the agent is restarted.
+    pub fn cancellation_token(self, token: CancellationToken) -> Self {
+        let mut agent = self;
+        agent.cancellation = token;
+        agent
+    }
+
+    /// A handle which can be used to wake up the agent so it polls the server immediately.
+    pub fn poll_trigger(&self) -> PollTrigger {
+        self.trigger.clone()
+    }
+
+    /// The handler of the agent.
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    /// The handler of the agent.
+    pub fn handler_mut(&mut self) -> &mut H {
+        &mut self.handler
+    }
+
+    /// Poll the server forever.
+    pub async fn run(&mut self) {
+        self.run_until(future::pending()).await
+    }
+
+    /// Poll the server until `shutdown` completes or the [`Agent::cancellation_token`] is cancelled.
+    ///
+    /// The shutdown is graceful: if a request from the server is being handled when
+    /// `shutdown` completes, it is processed to the end, including its feedback.
+    pub async fn run_until<F: Future<Output = ()>>(&mut self, shutdown: F) {
+        let cancellation = self.cancellation.clone();
+        let mut shutdown = Box::pin(future::select(
+            Box::pin(shutdown),
+            Box::pin(cancellation.cancelled()),
+        ));
+
+        loop {
+            let work = Box::pin(self.poll_once());
+            let (result, stop) = match future::select(work, shutdown.as_mut()).await {
+                Either::Left((result, _)) => (result, false),
+                Either::Right((_, work)) => (work.await, true),
+            };
+
+            if let Err(e) = &result {
+                self.handler.on_error(e);
+            }
+            if stop {
+                return;
+            }
+
+            let sleep = Box::pin(tokio::time::sleep(self.polling_sleep));
+            let triggered = Box::pin(self.trigger.triggered());
+            let wait = future::select(sleep, triggered);
+            if let Either::Right(_) = future::select(wait, shutdown.as_mut()).await {
+                return;
+            }
+        }
+    }
+
+    /// Poll the server once and handle all its pending requests.
+    ///
+    /// Return the sleeping time before the next poll, as suggested by the server
+    /// and adjusted by the [`PollingPolicy`] of the agent.
+    pub async fn poll_once(&mut self) -> Result<Duration, Error> {
+        let reply = self.client.poll().await?;
+        self.polling_sleep = reply.polling_sleep_with(&self.polling_policy)?;
+
+        if let Some(request) = reply.config_data_request() {
+            let data = self.handler.on_config_request().await;
+            request
+                .upload(Execution::Closed, Finished::Success, None, data, vec![])
+                .await?;
+        }
+
+        if let Some(confirmation) = reply.confirmation_base() {
+            self.confirm(confirmation).await?;
+        }
+
+        let mut cancel_handled = false;
+        if let Some(update) = reply.update() {
+            let update = update.fetch().await?;
+            cancel_handled = self.deploy(&update).await?;
+        }
+
+        if let Some(cancel_action) = reply.cancel_action() {
+            if !cancel_handled {
+                self.cancel(cancel_action).await?;
+            }
+        }
+
+        Ok(self.polling_sleep)
+    }
+
+    async fn confirm(&mut self, confirmation: ConfirmationRequest) -> Result<(), Error> {
+        let info = confirmation.update_info().await?;
+
+        match self.handler.on_confirmation(&info).await {
+            ConfirmationResponse::Confirmed => confirmation.confirm().await,
+            ConfirmationResponse::Denied => confirmation.decline().await,
+        }
+    }
+
+    /// Deploy `update`, returning whether a request to cancel it has been handled meanwhile.
+    async fn deploy(&mut self, update: &Update) -> Result<bool, Error> {
+        update
+            .send_feedback(Execution::Proceeding, Finished::None, vec![])
+            .await?;
+
+        let work = Box::pin(self.handler.on_update(update));
+        let cancel = future::select(
+            Box::pin(wait_for_cancel(
+                &self.client,
+                update.action_id(),
+                self.polling_sleep,
+                &self.polling_policy,
+            )),
+            Box::pin(self.cancellation.cancelled()),
+        );
+        let (result, cancel_action) = match future::select(work, cancel).await {
+            Either::Left((result, _)) => (result, None),
+            Either::Right((cancel, work)) => {
+                update.cancellation_token().cancel();
+                let cancel_action = match cancel {
+                    Either::Left((cancel_action, _)) => Some(cancel_action),
+                    Either::Right(_) => None,
+                };
+                (work.await, cancel_action)
+            }
+        };
+
+        // only an update which actually stopped because of the cancellation is cancelled
+        let interrupted = match &result {
+            Ok(()) => false,
+            Err(e) => update.cancellation_token().is_cancelled() && self.handler.is_interrupted(e),
+        };
+
+        if let Some(cancel_action) = &cancel_action {
+            cancel_action.conclude(interrupted).await?;
+            if interrupted {
+                // the action has been closed by its cancellation
+                return Ok(true);
+            }
+        }
+
+        match result {
+            Ok(()) => {
+                update
+                    .send_feedback(Execution::Closed, Finished::Success, vec![])
+                    .await?
+            }
+            // the agent is stopping, the update is deployed again once it is restarted
+            Err(_) if interrupted => {}
+            Err(e) => {
+                let details = e.to_string();
+                update
+                    .send_feedback(Execution::Closed, Finished::Failure, vec![&details])
+                    .await?
+            }
+        }
+
+        Ok(cancel_action.is_some())
+    }
+
+    async fn cancel(&mut self, cancel_action: CancelAction) -> Result<(), Error> {
+        let info = cancel_action.fetch().await?;
+
+        if self.handler.on_cancel(info.stop_id()).await {
+            info.accept(vec![]).await
+        } else {
+            info.reject(CANNOT_CANCEL).await
+        }
+    }
+}
@@ -0,0 +1,157 @@
+// Copyright 2025, Liebherr Digital Development Center GmbH.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// Authorization of the requests sent to the server
+
+use std::convert::TryInto;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use futures::future::BoxFuture;
+use reqwest::header::HeaderValue;
+
+use crate::ddi::client::{ClientAuthorization, Error};
+
+/// Provides the `Authorization` header sent with each request to the server.
+///
+/// The provider is queried before every request, so implementations can rotate
+/// the token without having to re-create the [`Client`](crate::ddi::Client) or any
+/// object retrieved from it.
+///
+/// [`ClientAuthorization`] implements this trait for static target and gateway tokens
+/// and [`FileToken`] reads the token from a file.
+///
+/// The methods are asynchronous so a provider can, for example, renew its token over
+/// the network without blocking the runtime. They return boxed futures so the provider
+/// can be shared by the client as a trait object, implementations would typically
+/// return `Box::pin(async move { ... })`.
+pub trait AuthProvider: fmt::Debug + Send + Sync {
+    /// The value of the `Authorization` header, or `None` if no header should be sent.
+    fn authorization(&self) -> BoxFuture<'_, Result<Option<HeaderValue>, Error>>;
+
+    /// Called when the server rejected a request with `401 Unauthorized`.
+    ///
+    /// Implementations can refresh their token and return `true` if the request
+    /// should be sent again with the new one. The default implementation returns `false`.
+    fn refresh(&self) -> BoxFuture<'_, Result<bool, Error>> {
+        Box::pin(async { Ok(false) })
+    }
+}
+
+impl ClientAuthorization {
+    /// The value of the `Authorization` header for this static token.
+    pub(crate) fn header(&self) -> Result<Option<HeaderValue>, Error> {
+        match self {
+            ClientAuthorization::TargetToken(key_token) => {
+                Ok(Some(format!("TargetToken {}", key_token).try_into()?))
+            }
+            ClientAuthorization::GatewayToken(key_token) => {
+                Ok(Some(format!("GatewayToken {}", key_token).try_into()?))
+            }
+            ClientAuthorization::None => Ok(None),
+        }
+    }
+}
+
+impl AuthProvider for ClientAuthorization {
+    fn authorization(&self) -> BoxFuture<'_, Result<Option<HeaderValue>, Error>> {
+        Box::pin(async move { self.header() })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum TokenKind {
+    Target,
+    Gateway,
+}
+
+#[derive(Debug)]
+struct CachedToken {
+    modified: Option<SystemTime>,
+    header: HeaderValue,
+}
+
+/// Authorization token read from a file.
+///
+/// The token is cached and the file is only read again when its modification time
+/// changed, or after the server rejected the token, so the token can be rotated by
+/// simply replacing the file. Leading and trailing whitespaces are ignored.
+#[derive(Debug)]
+pub struct FileToken {
+    path: PathBuf,
+    kind: TokenKind,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+impl FileToken {
+    /// Use the target token stored in the file at `path`.
+    pub fn target_token(path: &Path) -> Self {
+        Self::new(path, TokenKind::Target)
+    }
+
+    /// Use the gateway token stored in the file at `path`.
+    pub fn gateway_token(path: &Path) -> Self {
+        Self::new(path, TokenKind::Gateway)
+    }
+
+    fn new(path: &Path, kind: TokenKind) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            kind,
+            cached: Mutex::new(None),
+        }
+    }
+
+    async fn modified(&self) -> Result<Option<SystemTime>, Error> {
+        let metadata = tokio::fs::metadata(&self.path).await?;
+        // not all platforms support the modification time, the file is then only read again on refresh
+        Ok(metadata.modified().ok())
+    }
+
+    async fn read(&self, modified: Option<SystemTime>) -> Result<CachedToken, Error> {
+        let token = tokio::fs::read_to_string(&self.path).await?;
+        let token = token.trim();
+
+        let header = match self.kind {
+            TokenKind::Target => format!("TargetToken {}", token),
+            TokenKind::Gateway => format!("GatewayToken {}", token),
+        };
+
+        Ok(CachedToken {
+            modified,
+            header: header.try_into()?,
+        })
+    }
+}
+
+impl AuthProvider for FileToken {
+    fn authorization(&self) -> BoxFuture<'_, Result<Option<HeaderValue>, Error>> {
+        Box::pin(async move {
+            let modified = self.modified().await?;
+            if let Some(token) = self.cached.lock().unwrap().as_ref() {
+                if modified.is_some() && token.modified == modified {
+                    return Ok(Some(token.header.clone()));
+                }
+            }
+
+            let token = self.read(modified).await?;
+            let header = token.header.clone();
+            *self.cached.lock().unwrap() = Some(token);
+            Ok(Some(header))
+        })
+    }
+
+    fn refresh(&self) -> BoxFuture<'_, Result<bool, Error>> {
+        Box::pin(async move {
+            let token = self.read(self.modified().await?).await?;
+            let mut cached = self.cached.lock().unwrap();
+
+            let changed = !matches!(cached.as_ref(), Some(cached) if cached.header == token.header);
+            *cached = Some(token);
+
+            Ok(changed)
+        })
+    }
+}
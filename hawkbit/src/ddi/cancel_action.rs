@@ -3,37 +3,75 @@
 
 // Cancelled operation
 
-use reqwest::Client;
+use std::time::Duration;
+
 use serde::Deserialize;
+use tokio::sync::OnceCell;
 
-use crate::ddi::client::Error;
+use crate::ddi::client::{Client, Endpoint, Error, HttpClient};
 use crate::ddi::common::{send_feedback_internal, Execution, Finished};
+use crate::ddi::poll::PollingPolicy;
+
+/// Reason sent when rejecting a cancel action.
+pub(crate) const CANNOT_CANCEL: &str = "Action cannot be canceled anymore";
 
 /// A request from the server to cancel an update.
 ///
-/// Call [`CancelAction::id()`] to retrieve the ID of the action to cancel.
+/// Call [`CancelAction::fetch()`] to retrieve the details of the cancel action,
+/// which can then be accepted or rejected using [`CancelInfo`].
 ///
 /// Cancel actions need to be closed by sending feedback to the server using
 /// [`CancelAction::send_feedback`] with either
 /// [`Finished::Success`] or [`Finished::Failure`].
 #[derive(Debug)]
 pub struct CancelAction {
-    client: Client,
+    client: HttpClient,
     url: String,
+    reply: OnceCell<CancelReply>,
 }
 
 impl CancelAction {
-    pub(crate) fn new(client: Client, url: String) -> Self {
-        Self { client, url }
+    pub(crate) fn new(client: HttpClient, url: String) -> Self {
+        Self {
+            client,
+            url,
+            reply: OnceCell::new(),
+        }
+    }
+
+    /// The details of the cancel action, only retrieved from the server once.
+    async fn reply(&self) -> Result<&CancelReply, Error> {
+        self.reply
+            .get_or_try_init(|| async {
+                let reply = self
+                    .client
+                    .send(self.client.get(&self.url), Endpoint::CancelAction)
+                    .await?;
+
+                Ok(reply.json::<CancelReply>().await?)
+            })
+            .await
+    }
+
+    /// Retrieve the details of the cancel action.
+    ///
+    /// The details are only retrieved from the server once and then reused,
+    /// including when sending feedback.
+    pub async fn fetch(&self) -> Result<CancelInfo, Error> {
+        let reply = self.reply().await?;
+
+        Ok(CancelInfo {
+            client: self.client.clone(),
+            url: self.url.clone(),
+            id: reply.id.clone(),
+            stop_id: reply.cancel_action.stop_id.clone(),
+        })
     }
 
     /// Retrieve the id of the action to cancel.
     pub async fn id(&self) -> Result<String, Error> {
-        let reply = self.client.get(&self.url).send().await?;
-        reply.error_for_status_ref()?;
-
-        let reply = reply.json::<CancelReply>().await?;
-        Ok(reply.cancel_action.stop_id)
+        let reply = self.reply().await?;
+        Ok(reply.cancel_action.stop_id.clone())
     }
 
     /// Send feedback to server about this cancel action.
@@ -48,12 +86,76 @@ impl CancelAction {
         finished: Finished,
         details: Vec<&str>,
     ) -> Result<(), Error> {
-        let id = self.id().await?;
+        self.fetch()
+            .await?
+            .send_feedback(execution, finished, details)
+            .await
+    }
 
+    /// Confirm the cancellation to the server if the action has been `interrupted`, or reject it otherwise.
+    pub(crate) async fn conclude(&self, interrupted: bool) -> Result<(), Error> {
+        let info = self.fetch().await?;
+
+        if interrupted {
+            info.accept(vec![]).await
+        } else {
+            info.reject(CANNOT_CANCEL).await
+        }
+    }
+}
+
+/// The details of a [`CancelAction`].
+///
+/// The cancellation is closed using [`CancelInfo::accept`] or [`CancelInfo::reject`],
+/// which send the matching [`Execution`] and [`Finished`] status to the server.
+#[derive(Debug, Clone)]
+pub struct CancelInfo {
+    client: HttpClient,
+    url: String,
+    id: String,
+    stop_id: String,
+}
+
+impl CancelInfo {
+    /// The id of the cancel action.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The id of the action to cancel, such as [`Update::action_id`](crate::ddi::Update::action_id).
+    pub fn stop_id(&self) -> &str {
+        &self.stop_id
+    }
+
+    /// Report that the device is still cancelling the action, using [`Execution::Proceeding`].
+    pub async fn report_progress(&self, details: Vec<&str>) -> Result<(), Error> {
+        self.send_feedback(Execution::Proceeding, Finished::None, details)
+            .await
+    }
+
+    /// Confirm that the action has been cancelled, using [`Execution::Canceled`] and [`Finished::Success`].
+    pub async fn accept(self, details: Vec<&str>) -> Result<(), Error> {
+        self.send_feedback(Execution::Canceled, Finished::Success, details)
+            .await
+    }
+
+    /// Refuse to cancel the action for the given `reason`, for example because its
+    /// installation already started, using [`Execution::Rejected`] and [`Finished::Failure`].
+    pub async fn reject(self, reason: &str) -> Result<(), Error> {
+        self.send_feedback(Execution::Rejected, Finished::Failure, vec![reason])
+            .await
+    }
+
+    async fn send_feedback(
+        &self,
+        execution: Execution,
+        finished: Finished,
+        details: Vec<&str>,
+    ) -> Result<(), Error> {
         send_feedback_internal::<bool>(
             &self.client,
             &self.url,
-            &id,
+            &self.id,
             execution,
             finished,
             None,
@@ -63,7 +165,39 @@ impl CancelAction {
     }
 }
 
-#[allow(dead_code)]
+/// Poll the server until it requests the cancellation of the action `action_id`.
+///
+/// The first poll happens after `sleep`, the next ones after the polling interval
+/// suggested by the server, adjusted using `policy`.
+pub(crate) async fn wait_for_cancel(
+    client: &Client,
+    action_id: &str,
+    sleep: Duration,
+    policy: &PollingPolicy,
+) -> CancelAction {
+    let mut sleep = sleep;
+
+    loop {
+        tokio::time::sleep(sleep).await;
+
+        // errors are ignored as the action is still being processed, the server is polled again later
+        let reply = match client.poll().await {
+            Ok(reply) => reply,
+            Err(_) => continue,
+        };
+        if let Ok(interval) = reply.polling_sleep_with(policy) {
+            sleep = interval;
+        }
+
+        if let Some(cancel_action) = reply.cancel_action() {
+            let info = cancel_action.fetch().await;
+            if info.is_ok_and(|info| info.stop_id() == action_id) {
+                return cancel_action;
+            }
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct CancelReply {
     id: String,
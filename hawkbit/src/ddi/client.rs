@@ -3,23 +3,57 @@
 
 use std::convert::TryInto;
 
-use reqwest::Identity;
+use futures::Stream;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, LOCATION,
+};
+use reqwest::{Identity, IntoUrl, Method, RequestBuilder, Response, StatusCode};
 use std::fs::File;
 use std::io::Read;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use url::Url;
 
+use crate::ddi::auth::AuthProvider;
+use crate::ddi::download::TransportPreference;
+use crate::ddi::events::{self, DdiEvent};
+use crate::ddi::feedback_queue::FeedbackQueue;
 use crate::ddi::poll;
+use crate::ddi::rate_limit::RateLimiter;
+use crate::ddi::redirect::{self, RedirectPolicy};
+use crate::ddi::retry::{self, RetryPolicy};
+use crate::ddi::verification::VerificationPolicy;
 
 /// [Direct Device Integration](https://www.eclipse.org/hawkbit/apis/ddi_api/) client.
 #[derive(Debug, Clone)]
 pub struct Client {
     base_url: Url,
+    client: HttpClient,
+}
+
+/// HTTP client shared by all the objects retrieved from a [`Client`].
+///
+/// The authorization header is added to each request by [`HttpClient::send`]
+/// so the token can be updated by the [`AuthProvider`] at any time.
+#[derive(Debug, Clone)]
+pub(crate) struct HttpClient {
     client: reqwest::Client,
+    auth: Arc<dyn AuthProvider>,
+    retry: RetryPolicy,
+    feedback_queue: Option<Arc<FeedbackQueue>>,
+    rate_limiter: Option<RateLimiter>,
+    verification: VerificationPolicy,
+    transport: TransportPreference,
+    https_only: bool,
+    md5sum_check: bool,
+    headers: HeaderMap,
+    redirect: RedirectPolicy,
 }
 
 /// The method of Authorization for the client and the secret authentification token.
+///
+/// See [`AuthProvider`] for tokens which may change over time.
 #[derive(Debug, Clone)]
 pub enum ClientAuthorization {
     /// use a target token that is unique per target
@@ -49,20 +83,233 @@ pub enum Error {
     /// IO error
     #[error("IO error {0}")]
     Io(#[from] std::io::Error),
+    /// JSON error
+    #[error("JSON error {0}")]
+    Json(#[from] serde_json::Error),
+    /// The size of the downloaded artifact does not match the size announced by the server
+    #[error("Invalid artifact size: expected {expected} bytes, received {received}")]
+    SizeMismatch {
+        /// The size announced by the server
+        expected: u64,
+        /// The number of bytes received
+        received: u64,
+    },
+    /// The name of an artifact or chunk provided by the server cannot safely be used as a file name
+    #[error("Unsafe file name: {0:?}")]
+    UnsafeFilename(String),
+    /// The artifact has no download link which can be used
+    #[error("Missing download link for artifact")]
+    MissingDownloadLink,
+    /// The server redirected the request too many times, see [`RedirectPolicy::max_redirects`]
+    #[error("Too many redirects")]
+    TooManyRedirects,
+    /// The redirect to the given URL is not allowed by the [`RedirectPolicy`]
+    #[error("Redirect to {0} refused")]
+    RedirectRefused(String),
+    /// The MD5SUM file of an artifact cannot be parsed
+    #[error("Invalid MD5SUM file")]
+    InvalidMd5Sum,
+    /// The MD5SUM file of an artifact does not match the md5sum of the deployment
+    #[error("MD5SUM file mismatch: deployment has {deployment}, MD5SUM file has {md5sum}")]
+    Md5SumMismatch {
+        /// The md5sum provided in the deployment
+        deployment: String,
+        /// The md5sum provided in the MD5SUM file
+        md5sum: String,
+    },
+    /// The download has been interrupted by a cancellation token, see [`DownloadOptions::cancellation_token`](crate::ddi::DownloadOptions::cancellation_token)
+    #[error("Download canceled")]
+    Canceled,
     /// Invalid checksum
     #[cfg(feature = "hash-digest")]
     #[error("Invalid Checksum")]
-    ChecksumError(crate::ddi::deployment_base::ChecksumType),
+    ChecksumError(crate::ddi::verification::ChecksumType),
+    /// The server rejected the authorization of the request (401 or 403)
+    #[error("{endpoint} request unauthorized ({status})")]
+    Unauthorized {
+        /// The kind of endpoint the request was sent to
+        endpoint: Endpoint,
+        /// The HTTP status returned by the server
+        status: StatusCode,
+        /// The body of the reply, if any
+        body: Option<String>,
+    },
+    /// The resource does not exist on the server, for example because the action has been closed in the meantime (404 or 410)
+    #[error("{endpoint} resource not found ({status})")]
+    NotFound {
+        /// The kind of endpoint the request was sent to
+        endpoint: Endpoint,
+        /// The HTTP status returned by the server
+        status: StatusCode,
+        /// The body of the reply, if any
+        body: Option<String>,
+    },
+    /// The request conflicts with the current state of the resource on the server (409)
+    #[error("{endpoint} request conflicts with the server state ({status})")]
+    Conflict {
+        /// The kind of endpoint the request was sent to
+        endpoint: Endpoint,
+        /// The HTTP status returned by the server
+        status: StatusCode,
+        /// The body of the reply, if any
+        body: Option<String>,
+    },
+    /// The server is overloaded or down for maintenance (429 or 503)
+    #[error("server unavailable for {endpoint} request ({status})")]
+    ServerUnavailable {
+        /// The kind of endpoint the request was sent to
+        endpoint: Endpoint,
+        /// The HTTP status returned by the server
+        status: StatusCode,
+        /// The body of the reply, if any
+        body: Option<String>,
+        /// The delay requested by the server in the `Retry-After` header, if any
+        retry_after: Option<Duration>,
+    },
+    /// Any other error status returned by the server
+    #[error("{endpoint} request failed ({status})")]
+    HttpStatus {
+        /// The kind of endpoint the request was sent to
+        endpoint: Endpoint,
+        /// The HTTP status returned by the server
+        status: StatusCode,
+        /// The body of the reply, if any
+        body: Option<String>,
+    },
+}
+
+/// The kind of DDI endpoint a request is sent to.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+pub enum Endpoint {
+    /// Polling the root controller resource
+    #[strum(serialize = "poll")]
+    Poll,
+    /// Fetching the details of an update
+    #[strum(serialize = "deploymentBase")]
+    DeploymentBase,
+    /// Sending feedback about an action
+    #[strum(serialize = "feedback")]
+    Feedback,
+    /// Uploading the device configuration
+    #[strum(serialize = "configData")]
+    ConfigData,
+    /// Fetching the details of a cancel action
+    #[strum(serialize = "cancelAction")]
+    CancelAction,
+    /// Fetching the details of a confirmation request or answering it
+    #[strum(serialize = "confirmationBase")]
+    ConfirmationBase,
+    /// Downloading an artifact
+    #[strum(serialize = "artifact download")]
+    ArtifactDownload,
+}
+
+impl Error {
+    fn from_status(
+        endpoint: Endpoint,
+        status: StatusCode,
+        body: Option<String>,
+        retry_after: Option<Duration>,
+    ) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Error::Unauthorized {
+                endpoint,
+                status,
+                body,
+            },
+            StatusCode::NOT_FOUND | StatusCode::GONE => Error::NotFound {
+                endpoint,
+                status,
+                body,
+            },
+            StatusCode::CONFLICT => Error::Conflict {
+                endpoint,
+                status,
+                body,
+            },
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+                Error::ServerUnavailable {
+                    endpoint,
+                    status,
+                    body,
+                    retry_after,
+                }
+            }
+            _ => Error::HttpStatus {
+                endpoint,
+                status,
+                body,
+            },
+        }
+    }
+
+    /// The HTTP status returned by the server, if the error is caused by the server rejecting the request.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Unauthorized { status, .. }
+            | Error::NotFound { status, .. }
+            | Error::Conflict { status, .. }
+            | Error::ServerUnavailable { status, .. }
+            | Error::HttpStatus { status, .. } => Some(*status),
+            Error::ReqwestError(e) => e.status(),
+            _ => None,
+        }
+    }
+
+    /// Whether the operation may succeed if tried again later.
+    ///
+    /// This is the case for network errors, timeouts and errors on the server side.
+    /// Errors caused by the request itself, such as an invalid token or an action
+    /// which does not exist anymore, are not considered retryable.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::ServerUnavailable { .. } => true,
+            Error::HttpStatus { status, .. } => {
+                status.is_server_error() || *status == StatusCode::REQUEST_TIMEOUT
+            }
+            Error::ReqwestError(e) => {
+                e.is_timeout() || e.is_connect() || e.is_body() || body_interrupted(e)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Whether reading the response body failed because the connection was lost.
+///
+/// reqwest reports those as decoding errors, so they are told apart from invalid
+/// content by the I/O error that caused them.
+fn body_interrupted(error: &reqwest::Error) -> bool {
+    if !error.is_decode() {
+        return false;
+    }
+
+    let mut source = std::error::Error::source(error);
+    while let Some(err) = source {
+        if err.is::<std::io::Error>() {
+            return true;
+        }
+        source = err.source();
+    }
+    false
 }
 
 impl Client {
     /// Create a new DDI client.
     ///
+    /// This is a shortcut for [`Client::builder`] loading the certificates from files.
+    /// Use the [`ClientBuilder`] for more control over the client configuration.
+    ///
     /// # Arguments
     /// * `url`: the URL of the hawkBit server, such as `http://my-server.com:8080`
     /// * `tenant`: the server tenant
     /// * `controller_id`: the id of the controller
     /// * `authorization`: the authorization method and secret authentification token of the controller
+    /// * `server_cert`: path of a PEM file containing the server certificate, or a bundle of certificates if the file name ends with `.crt`.
+    ///   The built-in root certificates are disabled and only https connections are allowed if set.
+    /// * `client_cert`: path of a PEM file containing the client certificate and its private key
+    /// * `timeout`: timeout applied to both connecting to and reading from the server
     pub fn new(
         url: &str,
         tenant: &str,
@@ -72,36 +319,12 @@ impl Client {
         client_cert: Option<&str>,
         timeout: Option<Duration>,
     ) -> Result<Self, Error> {
-        let host: Url = url.parse()?;
-        let path = format!("{}/controller/v1/{}", tenant, controller_id);
-        let base_url = host.join(&path)?;
-
-        let mut client_builder = reqwest::Client::builder();
-
-        let mut headers = reqwest::header::HeaderMap::new();
-        match authorization {
-            ClientAuthorization::TargetToken(key_token) => {
-                headers.insert(
-                    reqwest::header::AUTHORIZATION,
-                    format!("TargetToken {}", &key_token).try_into()?,
-                );
-            }
-            ClientAuthorization::GatewayToken(key_token) => {
-                headers.insert(
-                    reqwest::header::AUTHORIZATION,
-                    format!("GatewayToken {}", &key_token).try_into()?,
-                );
-            }
-            ClientAuthorization::None => {
-                // no authorization header needed
-            }
-        }
+        let mut builder = Self::builder(url, tenant, controller_id).authorization(authorization);
 
         if let Some(cert_file) = client_cert {
             let mut buf = Vec::new();
             File::open(cert_file)?.read_to_end(&mut buf)?;
-            let identity = Identity::from_pem(&buf)?;
-            client_builder = client_builder.identity(identity);
+            builder = builder.identity_pem(&buf);
         }
 
         // Set the server certificate if provided
@@ -109,41 +332,659 @@ impl Client {
             let mut buf = Vec::new();
             File::open(cert_file)?.read_to_end(&mut buf)?;
 
-            if cert_file.ends_with(".crt") {
-                let certs = reqwest::Certificate::from_pem_bundle(&buf)?;
-                for cert in certs {
-                    client_builder = client_builder.add_root_certificate(cert);
-                }
+            builder = if cert_file.ends_with(".crt") {
+                builder.root_certificates_pem_bundle(&buf)
             } else {
-                let cert = reqwest::Certificate::from_pem(&buf)?;
-                client_builder = client_builder.add_root_certificate(cert);
-            }
+                builder.root_certificate_pem(&buf)
+            };
 
-            client_builder = client_builder
-                .tls_built_in_root_certs(false)
-                .https_only(true);
+            builder = builder.tls_built_in_root_certs(false).https_only(true);
         }
 
         // Add timeouts to all connections
         if let Some(timeout) = timeout {
-            client_builder = client_builder
-                .connect_timeout(timeout)
-                .read_timeout(timeout);
+            builder = builder.connect_timeout(timeout).read_timeout(timeout);
         }
 
-        let client = client_builder
-            .default_headers(headers)
-            .connection_verbose(true)
-            .build()?;
-        Ok(Self { base_url, client })
+        builder.build()
+    }
+
+    /// Start building a new DDI client.
+    ///
+    /// # Arguments
+    /// * `url`: the URL of the hawkBit server, such as `http://my-server.com:8080`
+    /// * `tenant`: the server tenant
+    /// * `controller_id`: the id of the controller
+    pub fn builder(url: &str, tenant: &str, controller_id: &str) -> ClientBuilder {
+        ClientBuilder::new(url, tenant, controller_id)
     }
 
     /// Poll the server for updates
+    ///
+    /// If the client has a [`FeedbackQueue`], the queued feedback is sent after a successful poll.
     pub async fn poll(&self) -> Result<poll::Reply, Error> {
-        let reply = self.client.get(self.base_url.clone()).send().await?;
-        reply.error_for_status_ref()?;
+        let reply = self
+            .client
+            .send(self.client.get(self.base_url.clone()), Endpoint::Poll)
+            .await?;
 
         let reply = reply.json::<poll::ReplyInternal>().await?;
+
+        if let Some(queue) = self.client.feedback_queue() {
+            // the server is reachable again, messages which cannot be sent yet are kept in the queue
+            let _ = queue.flush(&self.client).await;
+        }
+
         Ok(poll::Reply::new(reply, self.client.clone()))
     }
+
+    /// Send the feedback waiting in the [`FeedbackQueue`] of the client, if any.
+    ///
+    /// Messages are sent in order until one fails because of a transient error,
+    /// which is then returned.
+    pub async fn flush_feedback(&self) -> Result<(), Error> {
+        match self.client.feedback_queue() {
+            Some(queue) => queue.flush(&self.client).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Regularly poll the server and produce a `Stream` of [`DdiEvent`].
+    ///
+    /// The server is polled at the interval it suggests and an event is produced
+    /// only when a new request from the server shows up, not on every poll.
+    /// Errors are reported as part of the stream but do not end it: the server is
+    /// polled again after the usual interval.
+    pub fn events(&self) -> impl Stream<Item = Result<DdiEvent, Error>> {
+        events::events(self.clone())
+    }
+
+    pub(crate) fn http_client(&self) -> HttpClient {
+        self.client.clone()
+    }
+}
+
+impl HttpClient {
+    pub(crate) fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub(crate) fn post<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    pub(crate) fn put<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.put(url)
+    }
+
+    /// Send `request` to `endpoint` with the current authorization header.
+    ///
+    /// If the server replies with `401 Unauthorized` and the [`AuthProvider`] managed to
+    /// refresh its token, the request is sent again once with the new token.
+    /// Error statuses returned by the server are converted to the matching [`Error`].
+    ///
+    /// Failed requests are sent again according to the [`RetryPolicy`] of the client.
+    pub(crate) async fn send(
+        &self,
+        request: RequestBuilder,
+        endpoint: Endpoint,
+    ) -> Result<Response, Error> {
+        let mut request = request.build()?;
+        let replay_safe = retry::is_replay_safe(request.method(), endpoint);
+        let start = Instant::now();
+        let mut attempt = 1;
+
+        loop {
+            // requests with a streaming body cannot be sent again
+            let next = request.try_clone();
+
+            let err = match self.send_once(request, endpoint).await {
+                Ok(resp) => return Ok(resp),
+                Err(err) => err,
+            };
+
+            let delay = self
+                .retry
+                .delay(&err, attempt, start.elapsed(), endpoint, replay_safe);
+            match (next, delay) {
+                (Some(next), Some(delay)) => {
+                    tokio::time::sleep(delay).await;
+                    request = next;
+                    attempt += 1;
+                }
+                _ => return Err(err),
+            }
+        }
+    }
+
+    /// The retry policy of the client.
+    pub(crate) fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// The queue used to store the feedback which cannot be sent, if any.
+    pub(crate) fn feedback_queue(&self) -> Option<&FeedbackQueue> {
+        self.feedback_queue.as_deref()
+    }
+
+    /// The limiter of the bandwidth used by downloads, if any.
+    pub(crate) fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_ref()
+    }
+
+    /// The checksums verified when downloading artifacts.
+    pub(crate) fn verification_policy(&self) -> &VerificationPolicy {
+        &self.verification
+    }
+
+    /// The links used to download artifacts.
+    pub(crate) fn transport_preference(&self) -> TransportPreference {
+        self.transport
+    }
+
+    /// Whether the MD5SUM file of artifacts is checked before downloading them.
+    pub(crate) fn checks_md5sum_file(&self) -> bool {
+        self.md5sum_check
+    }
+
+    /// Whether artifacts can be downloaded over plain http.
+    pub(crate) fn allows_http(&self) -> bool {
+        !self.https_only && self.transport != TransportPreference::HttpsOnly
+    }
+
+    /// Send `request` to `endpoint` once, without applying the [`RetryPolicy`].
+    ///
+    /// The request is only sent again if the [`AuthProvider`] refreshed its token.
+    pub(crate) async fn send_once(
+        &self,
+        request: reqwest::Request,
+        endpoint: Endpoint,
+    ) -> Result<Response, Error> {
+        let retry = request.try_clone();
+
+        let mut resp = self.execute(request, endpoint).await?;
+        if let Some(retry) = retry {
+            if resp.status() == StatusCode::UNAUTHORIZED && self.auth.refresh().await? {
+                resp = self.execute(retry, endpoint).await?;
+            }
+        }
+
+        let status = resp.status();
+        if status.is_client_error() || status.is_server_error() {
+            let retry_after = retry::retry_after(resp.headers());
+            let body = resp.text().await.ok().filter(|body| !body.is_empty());
+            return Err(Error::from_status(endpoint, status, body, retry_after));
+        }
+
+        Ok(resp)
+    }
+
+    /// Execute `request`, following the redirects according to the [`RedirectPolicy`].
+    ///
+    /// A redirect which cannot be followed, because it has no location or the body
+    /// of the request cannot be sent again, is reported as [`Error::HttpStatus`].
+    async fn execute(
+        &self,
+        request: reqwest::Request,
+        endpoint: Endpoint,
+    ) -> Result<Response, Error> {
+        let origin = request.url().origin();
+        let authorization = self.auth.authorization().await?;
+        let mut request = request;
+        let mut hops = 0;
+
+        loop {
+            // keep a copy without credentials to follow a redirect
+            let next = request.try_clone();
+
+            // credentials are only sent to the origin of the request
+            if request.url().origin() == origin {
+                let headers = request.headers_mut();
+                for name in self.headers.keys() {
+                    if !headers.contains_key(name) {
+                        for value in self.headers.get_all(name) {
+                            headers.append(name, value.clone());
+                        }
+                    }
+                }
+                if let Some(value) = &authorization {
+                    headers.insert(AUTHORIZATION, value.clone());
+                }
+            }
+
+            let resp = self.client.execute(request).await?;
+            if !redirect::is_redirect(resp.status()) {
+                return Ok(resp);
+            }
+            let (location, mut next) = match (resp.headers().get(LOCATION), next) {
+                (Some(location), Some(next)) => (location, next),
+                _ => {
+                    let status = resp.status();
+                    let body = resp.text().await.ok().filter(|body| !body.is_empty());
+                    return Err(Error::HttpStatus {
+                        endpoint,
+                        status,
+                        body,
+                    });
+                }
+            };
+
+            let url = location
+                .to_str()
+                .map_err(|_| Error::RedirectRefused(format!("{:?}", location)))?;
+            let url = resp.url().join(url)?;
+            self.redirect.check(resp.url(), &url, hops)?;
+            hops += 1;
+
+            let status = resp.status();
+            if status == StatusCode::SEE_OTHER
+                || (status != StatusCode::TEMPORARY_REDIRECT
+                    && status != StatusCode::PERMANENT_REDIRECT
+                    && next.method() == Method::POST)
+            {
+                *next.method_mut() = Method::GET;
+                *next.body_mut() = None;
+                next.headers_mut().remove(CONTENT_TYPE);
+                next.headers_mut().remove(CONTENT_LENGTH);
+            }
+            if next.body().is_some() {
+                self.redirect.check_body(&origin, &url)?;
+            }
+            *next.url_mut() = url;
+            request = next;
+        }
+    }
+}
+
+/// TLS certificate provided to the [`ClientBuilder`], parsed when building the client.
+#[derive(Debug, Clone)]
+enum CertificateSource {
+    Pem(Vec<u8>),
+    PemBundle(Vec<u8>),
+    Der(Vec<u8>),
+}
+
+/// Builder of [`Client`].
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use hawkbit::ddi::{Client, ClientAuthorization};
+///
+/// let client = Client::builder("http://my-server.com:8080", "DEFAULT", "my-device")
+///     .authorization(ClientAuthorization::TargetToken("secret".to_string()))
+///     .connect_timeout(Duration::from_secs(10))
+///     .user_agent("my-agent/1.0")
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    url: String,
+    tenant: String,
+    controller_id: String,
+    authorization: Option<ClientAuthorization>,
+    auth_provider: Option<Arc<dyn AuthProvider>>,
+    root_certificates: Vec<CertificateSource>,
+    identity: Option<Vec<u8>>,
+    built_in_root_certs: bool,
+    https_only: bool,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    headers: HeaderMap,
+    user_agent: Option<String>,
+    retry: RetryPolicy,
+    feedback_queue: Option<Arc<FeedbackQueue>>,
+    rate_limiter: Option<RateLimiter>,
+    verification: VerificationPolicy,
+    transport: TransportPreference,
+    md5sum_check: bool,
+    redirect: RedirectPolicy,
+}
+
+impl ClientBuilder {
+    fn new(url: &str, tenant: &str, controller_id: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            tenant: tenant.to_string(),
+            controller_id: controller_id.to_string(),
+            authorization: None,
+            auth_provider: None,
+            root_certificates: Vec::new(),
+            identity: None,
+            built_in_root_certs: true,
+            https_only: false,
+            connect_timeout: None,
+            read_timeout: None,
+            timeout: None,
+            headers: HeaderMap::new(),
+            user_agent: None,
+            retry: RetryPolicy::none(),
+            feedback_queue: None,
+            rate_limiter: None,
+            verification: VerificationPolicy::default(),
+            transport: TransportPreference::default(),
+            md5sum_check: false,
+            redirect: RedirectPolicy::default(),
+        }
+    }
+
+    /// Set the authorization method and secret authentification token of the controller,
+    /// default to [`ClientAuthorization::None`].
+    ///
+    /// Replaces any provider previously set using [`ClientBuilder::auth_provider`].
+    pub fn authorization(self, authorization: ClientAuthorization) -> Self {
+        let mut builder = self;
+        builder.authorization = Some(authorization);
+        builder.auth_provider = None;
+        builder
+    }
+
+    /// Set a custom provider of the authorization header, see [`AuthProvider`].
+    ///
+    /// Replaces any authorization previously set using [`ClientBuilder::authorization`].
+    pub fn auth_provider<A: AuthProvider + 'static>(self, provider: A) -> Self {
+        let mut builder = self;
+        builder.auth_provider = Some(Arc::new(provider));
+        builder.authorization = None;
+        builder
+    }
+
+    /// Trust the PEM encoded certificate `pem` when connecting to the server.
+    pub fn root_certificate_pem(self, pem: &[u8]) -> Self {
+        let mut builder = self;
+        builder
+            .root_certificates
+            .push(CertificateSource::Pem(pem.to_vec()));
+        builder
+    }
+
+    /// Trust all the certificates of the PEM encoded bundle `pem_bundle` when connecting to the server.
+    pub fn root_certificates_pem_bundle(self, pem_bundle: &[u8]) -> Self {
+        let mut builder = self;
+        builder
+            .root_certificates
+            .push(CertificateSource::PemBundle(pem_bundle.to_vec()));
+        builder
+    }
+
+    /// Trust the DER encoded certificate `der` when connecting to the server.
+    pub fn root_certificate_der(self, der: &[u8]) -> Self {
+        let mut builder = self;
+        builder
+            .root_certificates
+            .push(CertificateSource::Der(der.to_vec()));
+        builder
+    }
+
+    /// Set the client identity used for mutual TLS authentication.
+    ///
+    /// `pem` must contain the PEM encoded private key and at least one certificate.
+    pub fn identity_pem(self, pem: &[u8]) -> Self {
+        let mut builder = self;
+        builder.identity = Some(pem.to_vec());
+        builder
+    }
+
+    /// Set whether the built-in root certificates should be trusted, default to `true`.
+    pub fn tls_built_in_root_certs(self, enabled: bool) -> Self {
+        let mut builder = self;
+        builder.built_in_root_certs = enabled;
+        builder
+    }
+
+    /// Only allow https connections, default to `false`.
+    pub fn https_only(self, enabled: bool) -> Self {
+        let mut builder = self;
+        builder.https_only = enabled;
+        builder
+    }
+
+    /// Set the timeout for establishing connections to the server.
+    pub fn connect_timeout(self, timeout: Duration) -> Self {
+        let mut builder = self;
+        builder.connect_timeout = Some(timeout);
+        builder
+    }
+
+    /// Set the timeout for each read operation from the server.
+    pub fn read_timeout(self, timeout: Duration) -> Self {
+        let mut builder = self;
+        builder.read_timeout = Some(timeout);
+        builder
+    }
+
+    /// Set the timeout for a whole request, from connecting until the body has been read.
+    ///
+    /// Be careful when downloading big artifacts as this timeout also applies to them.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        let mut builder = self;
+        builder.timeout = Some(timeout);
+        builder
+    }
+
+    /// Add a header sent with each request to the server.
+    pub fn default_header(self, name: HeaderName, value: HeaderValue) -> Self {
+        let mut builder = self;
+        builder.headers.insert(name, value);
+        builder
+    }
+
+    /// Set the `User-Agent` header sent with each request to the server.
+    pub fn user_agent(self, user_agent: &str) -> Self {
+        let mut builder = self;
+        builder.user_agent = Some(user_agent.to_string());
+        builder
+    }
+
+    /// Set the policy used to retry requests failing because of transient errors,
+    /// default to [`RetryPolicy::none`].
+    pub fn retry_policy(self, policy: RetryPolicy) -> Self {
+        let mut builder = self;
+        builder.retry = policy;
+        builder
+    }
+
+    /// Store the feedback which cannot be sent because of transient errors in `queue`,
+    /// to send it again later, see [`FeedbackQueue`].
+    pub fn feedback_queue(self, queue: FeedbackQueue) -> Self {
+        let mut builder = self;
+        builder.feedback_queue = Some(Arc::new(queue));
+        builder
+    }
+
+    /// Limit the bandwidth used by all the artifact downloads, see [`RateLimiter`].
+    pub fn rate_limiter(self, limiter: RateLimiter) -> Self {
+        let mut builder = self;
+        builder.rate_limiter = Some(limiter);
+        builder
+    }
+
+    /// Set the checksums verified when downloading artifacts,
+    /// default to [`VerificationPolicy::All`].
+    pub fn verification_policy(self, policy: VerificationPolicy) -> Self {
+        let mut builder = self;
+        builder.verification = policy;
+        builder
+    }
+
+    /// Set which links are used to download artifacts,
+    /// default to [`TransportPreference::PreferHttps`].
+    pub fn transport_preference(self, preference: TransportPreference) -> Self {
+        let mut builder = self;
+        builder.transport = preference;
+        builder
+    }
+
+    /// Check the MD5SUM file served for each artifact against the md5sum of the deployment
+    /// before downloading it, default to `false`.
+    ///
+    /// This provides a second source of integrity, see [`Artifact::check_md5sum_file`](crate::ddi::Artifact::check_md5sum_file).
+    pub fn check_md5sum_file(self, enabled: bool) -> Self {
+        let mut builder = self;
+        builder.md5sum_check = enabled;
+        builder
+    }
+
+    /// Set the policy used to follow the redirects returned by the server,
+    /// default to [`RedirectPolicy::default`].
+    pub fn redirect_policy(self, policy: RedirectPolicy) -> Self {
+        let mut builder = self;
+        builder.redirect = policy;
+        builder
+    }
+
+    /// Create the [`Client`].
+    pub fn build(self) -> Result<Client, Error> {
+        let host: Url = self.url.parse()?;
+        let path = format!("{}/controller/v1/{}", self.tenant, self.controller_id);
+        let base_url = host.join(&path)?;
+
+        let mut client_builder = reqwest::Client::builder();
+
+        let auth: Arc<dyn AuthProvider> = match (self.authorization, self.auth_provider) {
+            (_, Some(provider)) => provider,
+            (Some(authorization), None) => {
+                // check the token format early as it is not going to change
+                authorization.header()?;
+                Arc::new(authorization)
+            }
+            (None, None) => Arc::new(ClientAuthorization::None),
+        };
+
+        if let Some(pem) = self.identity {
+            let identity = Identity::from_pem(&pem)?;
+            client_builder = client_builder.identity(identity);
+        }
+
+        for source in self.root_certificates {
+            let certs = match source {
+                CertificateSource::Pem(pem) => vec![reqwest::Certificate::from_pem(&pem)?],
+                CertificateSource::PemBundle(pem) => reqwest::Certificate::from_pem_bundle(&pem)?,
+                CertificateSource::Der(der) => vec![reqwest::Certificate::from_der(&der)?],
+            };
+            for cert in certs {
+                client_builder = client_builder.add_root_certificate(cert);
+            }
+        }
+
+        client_builder = client_builder
+            .tls_built_in_root_certs(self.built_in_root_certs)
+            .https_only(self.https_only);
+
+        if let Some(timeout) = self.connect_timeout {
+            client_builder = client_builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.read_timeout {
+            client_builder = client_builder.read_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            client_builder = client_builder.timeout(timeout);
+        }
+        if let Some(user_agent) = self.user_agent {
+            let user_agent: HeaderValue = user_agent.try_into()?;
+            client_builder = client_builder.user_agent(user_agent);
+        }
+
+        // redirects are followed by HttpClient so credentials are not sent to other hosts
+        let client = client_builder
+            .redirect(reqwest::redirect::Policy::none())
+            .connection_verbose(true)
+            .build()?;
+        Ok(Client {
+            base_url,
+            client: HttpClient {
+                client,
+                auth,
+                retry: self.retry,
+                feedback_queue: self.feedback_queue,
+                rate_limiter: self.rate_limiter,
+                verification: self.verification,
+                transport: self.transport,
+                https_only: self.https_only,
+                md5sum_check: self.md5sum_check,
+                headers: self.headers,
+                redirect: self.redirect,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::prelude::*;
+
+    #[tokio::test]
+    async fn redirect_streaming_body() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(PUT).path("/config");
+            then.status(307).header("Location", "/moved");
+        });
+        let moved = server.mock(|when, then| {
+            when.path("/moved");
+            then.status(200);
+        });
+
+        let client = Client::builder(&server.base_url(), "DEFAULT", "Target1")
+            .build()
+            .expect("DDI creation failed");
+        // a streaming body cannot be sent again to the new location
+        let body = futures::stream::iter(vec![Ok::<_, std::io::Error>("data")]);
+        let request = client
+            .client
+            .put(server.url("/config"))
+            .body(reqwest::Body::wrap_stream(body));
+
+        assert!(matches!(
+            client.client.send(request, Endpoint::ConfigData).await,
+            Err(Error::HttpStatus {
+                endpoint: Endpoint::ConfigData,
+                status: StatusCode::TEMPORARY_REDIRECT,
+                ..
+            })
+        ));
+        assert_eq!(moved.calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn redirect_body_other_origin() {
+        let storage = MockServer::start();
+        let stored = storage.mock(|when, then| {
+            when.method(PUT).path("/config");
+            then.status(200);
+        });
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(PUT).path("/config");
+            then.status(307).header("Location", storage.url("/config"));
+        });
+
+        let put = |policy: RedirectPolicy| async {
+            let client = Client::builder(&server.base_url(), "DEFAULT", "Target1")
+                .redirect_policy(policy)
+                .build()
+                .expect("DDI creation failed");
+            let request = client
+                .client
+                .put(server.url("/config"))
+                .json(&serde_json::json!({"secret": true}));
+            client.client.send(request, Endpoint::ConfigData).await
+        };
+
+        // the body is not sent to another origin unless its host is allowed
+        assert!(matches!(
+            put(RedirectPolicy::default()).await,
+            Err(Error::RedirectRefused(url)) if url == storage.url("/config")
+        ));
+        assert_eq!(stored.calls(), 0);
+
+        put(RedirectPolicy::default().allowed_hosts(&["127.0.0.1"]))
+            .await
+            .expect("redirect not followed");
+        assert_eq!(stored.calls(), 1);
+    }
 }
@@ -1,16 +1,18 @@
 // Copyright 2020, Collabora Ltd.
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use std::collections::hash_map::RandomState;
 use std::fmt;
+use std::hash::{BuildHasher, Hasher};
 
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
-use crate::ddi::client::Error;
+use crate::ddi::client::{Endpoint, Error, HttpClient};
 use crate::ddi::feedback::Feedback;
+use crate::ddi::feedback_queue::QueuedFeedback;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Link {
     href: String,
 }
@@ -21,7 +23,7 @@ impl fmt::Display for Link {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 /// Sent by the target to the server informing it about the execution state of a pending request,
 /// see the [DDI API reference](https://www.eclipse.org/hawkbit/apis/ddi_api/) for details.
@@ -44,7 +46,7 @@ pub enum Execution {
     Download,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 /// Status of a pending operation
 pub enum Finished {
@@ -57,7 +59,7 @@ pub enum Finished {
 }
 
 pub(crate) async fn send_feedback_internal<T: Serialize>(
-    client: &Client,
+    client: &HttpClient,
     url: &str,
     id: &str,
     execution: Execution,
@@ -65,6 +67,49 @@ pub(crate) async fn send_feedback_internal<T: Serialize>(
     progress: Option<T>,
     details: Vec<&str>,
 ) -> Result<(), Error> {
+    let url = feedback_url(url)?;
+    let details = details.iter().map(|m| m.to_string()).collect();
+    let feedback = Feedback::new(id, execution, finished, progress, details);
+
+    if let Some(queue) = client.feedback_queue() {
+        let feedback =
+            QueuedFeedback::new(url.to_string(), finished, serde_json::to_value(&feedback)?);
+        return queue.send(client, feedback).await;
+    }
+
+    client
+        .send(
+            client.post(url.to_string()).json(&feedback),
+            Endpoint::Feedback,
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Send feedback reporting the progress of an action, trying only once.
+///
+/// The progress is not worth delaying the caller for, so the request is not retried
+/// and does not go through the feedback queue of the client.
+pub(crate) async fn send_progress_feedback<T: Serialize>(
+    client: &HttpClient,
+    url: &str,
+    id: &str,
+    execution: Execution,
+    progress: T,
+    details: Vec<&str>,
+) -> Result<(), Error> {
+    let url = feedback_url(url)?;
+    let details = details.iter().map(|m| m.to_string()).collect();
+    let feedback = Feedback::new(id, execution, Finished::None, Some(progress), details);
+
+    let request = client.post(url.to_string()).json(&feedback).build()?;
+    client.send_once(request, Endpoint::Feedback).await?;
+
+    Ok(())
+}
+
+fn feedback_url(url: &str) -> Result<Url, Error> {
     let mut url: Url = url.parse()?;
     {
         let mut paths = url
@@ -73,12 +118,12 @@ pub(crate) async fn send_feedback_internal<T: Serialize>(
         paths.push("feedback");
     }
     url.set_query(None);
+    Ok(url)
+}
 
-    let details = details.iter().map(|m| m.to_string()).collect();
-    let feedback = Feedback::new(id, execution, finished, progress, details);
-
-    let reply = client.post(url.to_string()).json(&feedback).send().await?;
-    reply.error_for_status()?;
-
-    Ok(())
+/// A random number in `[0, 1)`, good enough to add jitter to delays.
+pub(crate) fn random_fraction() -> f64 {
+    // each RandomState is seeded with different random keys
+    let hash = RandomState::new().build_hasher().finish();
+    (hash >> 11) as f64 / (1u64 << 53) as f64
 }
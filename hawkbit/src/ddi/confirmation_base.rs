@@ -1,10 +1,10 @@
 // Copyright 2025, Liebherr Digital Development Center GmbH.
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use reqwest::{Client, Url};
+use reqwest::Url;
 use serde::{Deserialize, Serialize};
 
-use crate::ddi::client::Error;
+use crate::ddi::client::{Endpoint, Error, HttpClient};
 use crate::ddi::deployment_base::{Chunk, Deployment};
 
 #[derive(Debug)]
@@ -13,13 +13,13 @@ use crate::ddi::deployment_base::{Chunk, Deployment};
 ///
 /// Call [`ConfirmationRequest::fetch()`] to retrieve the details from server.
 pub struct ConfirmationRequest {
-    client: Client,
+    client: HttpClient,
     url: String,
     details: Vec<String>,
 }
 
 impl ConfirmationRequest {
-    pub(crate) fn new(client: Client, url: String) -> Self {
+    pub(crate) fn new(client: HttpClient, url: String) -> Self {
         Self {
             client,
             url,
@@ -41,13 +41,12 @@ impl ConfirmationRequest {
         }
         url.set_query(None);
 
-        let reply = self
-            .client
-            .post(url.to_string())
-            .json(&confirmation)
-            .send()
+        self.client
+            .send(
+                self.client.post(url.to_string()).json(&confirmation),
+                Endpoint::ConfirmationBase,
+            )
             .await?;
-        reply.error_for_status_ref()?;
         Ok(())
     }
 
@@ -65,20 +64,21 @@ impl ConfirmationRequest {
         }
         url.set_query(None);
 
-        let reply = self
-            .client
-            .post(url.to_string())
-            .json(&confirmation)
-            .send()
+        self.client
+            .send(
+                self.client.post(url.to_string()).json(&confirmation),
+                Endpoint::ConfirmationBase,
+            )
             .await?;
-        reply.error_for_status_ref()?;
         Ok(())
     }
 
     /// Fetch the details of the update to be confirmed
     pub async fn update_info(&self) -> Result<ConfirmationInfo, Error> {
-        let reply = self.client.get(&self.url).send().await?;
-        reply.error_for_status_ref()?;
+        let reply = self
+            .client
+            .send(self.client.get(&self.url), Endpoint::ConfirmationBase)
+            .await?;
 
         let reply: Reply = reply.json().await?;
         Ok(ConfirmationInfo {
@@ -99,7 +99,7 @@ impl ConfirmationRequest {
             .confirmation
             .chunks
             .iter()
-            .map(move |c| Chunk::new(c, client.clone()))
+            .map(move |c| Chunk::new(c, client.clone(), None))
             .collect();
 
         // collect all metadata of each chunk
@@ -116,7 +116,7 @@ impl ConfirmationRequest {
 /// The downloaded details of a confirmation request.
 #[derive(Debug)]
 pub struct ConfirmationInfo {
-    client: Client,
+    client: HttpClient,
     reply: Reply,
 }
 
@@ -129,7 +129,7 @@ impl ConfirmationInfo {
             .confirmation
             .chunks
             .iter()
-            .map(move |c| Chunk::new(c, self.client.clone()))
+            .map(move |c| Chunk::new(c, self.client.clone(), None))
             .collect();
 
         // collect all metadata of each chunk
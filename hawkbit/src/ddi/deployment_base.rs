@@ -3,23 +3,41 @@
 
 // Structures when querying deployment
 
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use bytes::Bytes;
+use futures::future::{self, Either};
 use futures::{prelude::*, TryStreamExt};
 use reqwest::header::RANGE;
-use reqwest::{Client, Response};
+use reqwest::{Response, StatusCode};
 use serde::de::{Deserializer, Error as _, IgnoredAny, MapAccess, Visitor};
+use serde::ser::{SerializeMap, Serializer};
 use serde::{Deserialize, Serialize};
 
 use tokio::fs::OpenOptions;
+use tokio::sync::Mutex;
 use tokio::{
     fs::{DirBuilder, File},
-    io::AsyncWriteExt,
+    io::{AsyncWrite, AsyncWriteExt},
 };
+use tokio_util::sync::CancellationToken;
 
-use crate::ddi::client::Error;
+use crate::ddi::cancel_action::wait_for_cancel;
+use crate::ddi::client::{Client, Endpoint, Error, HttpClient};
 use crate::ddi::common::{send_feedback_internal, Execution, Finished, Link};
+use crate::ddi::download::{ActionRef, DownloadOptions, Tracker, TransportPreference};
+use crate::ddi::persist::sync_dir;
+use crate::ddi::poll::PollingPolicy;
+use crate::ddi::rate_limit::RateLimiter;
+use crate::ddi::state::{ActionPhase, ActionState};
+#[cfg(any(feature = "hash-md5", feature = "hash-sha1", feature = "hash-sha256"))]
+use crate::ddi::verification::ChecksumType;
+use crate::ddi::verification::{VerificationPolicy, Verifier};
+
+const HASH_BUFFER_SIZE: usize = 4096;
 
 /// Get the file size from metadata in a platform independent way
 fn file_size(metadata: &std::fs::Metadata) -> u64 {
@@ -35,24 +53,41 @@ fn file_size(metadata: &std::fs::Metadata) -> u64 {
     }
 }
 
+/// The `.part` file used while downloading to `path`.
+fn part_file(path: &Path) -> PathBuf {
+    let mut part = path.as_os_str().to_os_string();
+    part.push(".part");
+    PathBuf::from(part)
+}
+
+/// Remove the `.part` file used while downloading to `path`, if any.
+async fn remove_part_file(path: &Path) -> Result<(), Error> {
+    match tokio::fs::remove_file(part_file(path)).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
 #[derive(Debug)]
 /// A pending update whose details have not been retrieved yet.
 ///
 /// Call [`UpdatePreFetch::fetch()`] to retrieve the details from server.
 pub struct UpdatePreFetch {
-    client: Client,
+    client: HttpClient,
     url: String,
 }
 
 impl UpdatePreFetch {
-    pub(crate) fn new(client: Client, url: String) -> Self {
+    pub(crate) fn new(client: HttpClient, url: String) -> Self {
         Self { client, url }
     }
 
     /// Retrieve details about the update.
     pub async fn fetch(self) -> Result<Update, Error> {
-        let reply = self.client.get(&self.url).send().await?;
-        reply.error_for_status_ref()?;
+        let reply = self
+            .client
+            .send(self.client.get(&self.url), Endpoint::DeploymentBase)
+            .await?;
 
         let reply = reply.json::<Reply>().await?;
         Ok(Update::new(self.client, reply, self.url))
@@ -68,7 +103,7 @@ struct Reply {
     action_history: Option<ActionHistory>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub(crate) struct Deployment {
     download: Type,
     update: Type,
@@ -99,7 +134,7 @@ pub enum MaintenanceWindow {
     Unavailable,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub(crate) struct ChunkInternal {
     #[serde(default)]
     metadata: Vec<Metadata>,
@@ -109,29 +144,100 @@ pub(crate) struct ChunkInternal {
     artifacts: Vec<ArtifactInternal>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 struct Metadata {
     key: String,
     value: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 struct ArtifactInternal {
     filename: String,
     hashes: Hashes,
-    size: u32,
+    size: u64,
     #[serde(rename = "_links")]
     links: Links,
 }
 
-#[derive(Debug, Deserialize, Clone)]
-#[allow(dead_code)]
-struct Hashes {
+/// Checksums of an artifact, as provided by the server.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct Hashes {
     sha1: String,
     md5: String,
     sha256: String,
 }
 
+impl Hashes {
+    /// Create the checksums from their hexadecimal representation,
+    /// for example to restore a [`DownloadedArtifact`].
+    pub fn new(md5: &str, sha1: &str, sha256: &str) -> Self {
+        Self {
+            md5: md5.to_string(),
+            sha1: sha1.to_string(),
+            sha256: sha256.to_string(),
+        }
+    }
+
+    /// The md5sum of the artifact, in hexadecimal.
+    pub fn md5(&self) -> &str {
+        &self.md5
+    }
+
+    /// The sha1sum of the artifact, in hexadecimal.
+    pub fn sha1(&self) -> &str {
+        &self.sha1
+    }
+
+    /// The sha256sum of the artifact, in hexadecimal.
+    pub fn sha256(&self) -> &str {
+        &self.sha256
+    }
+}
+
+/// Content of the MD5SUM file of an artifact, see [`Artifact::fetch_md5sum_file`].
+///
+/// The file uses the format of the `md5sum` tool: `<md5sum>  <filename>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Md5Sum {
+    md5: String,
+    filename: Option<String>,
+}
+
+impl Md5Sum {
+    /// The md5sum of the artifact, in hexadecimal.
+    pub fn md5(&self) -> &str {
+        &self.md5
+    }
+
+    /// The name of the artifact file, if provided.
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+}
+
+impl std::str::FromStr for Md5Sum {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let line = s.lines().next().unwrap_or_default().trim();
+        let (md5, filename) = match line.split_once(char::is_whitespace) {
+            Some((md5, filename)) => (md5, filename.trim_start()),
+            None => (line, ""),
+        };
+
+        if md5.len() != 32 || !md5.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Error::InvalidMd5Sum);
+        }
+
+        // binary mode is marked by a '*' before the file name
+        let filename = filename.strip_prefix('*').unwrap_or(filename);
+        Ok(Self {
+            md5: md5.to_ascii_lowercase(),
+            filename: (!filename.is_empty()).then(|| filename.to_string()),
+        })
+    }
+}
+
 impl<'de> Deserialize<'de> for Links {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -207,8 +313,30 @@ impl<'de> Deserialize<'de> for Links {
     }
 }
 
-#[derive(Debug)]
-#[allow(dead_code)]
+impl Serialize for Links {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // use the same format as the server so the links can be deserialized again
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(https) = &self.https {
+            map.serialize_entry("download", &https.content)?;
+            if let Some(md5sum) = &https.md5sum {
+                map.serialize_entry("md5sum", md5sum)?;
+            }
+        }
+        if let Some(http) = &self.http {
+            map.serialize_entry("download-http", &http.content)?;
+            if let Some(md5sum) = &http.md5sum {
+                map.serialize_entry("md5sum-http", md5sum)?;
+            }
+        }
+        map.end()
+    }
+}
+
+#[derive(Debug, Clone)]
 struct Download {
     content: Link,
     md5sum: Option<Link>,
@@ -216,7 +344,7 @@ struct Download {
 
 /// Download links a single artifact, at least one of http or https will be
 /// Some
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Links {
     http: Option<Download>,
     https: Option<Download>,
@@ -233,14 +361,45 @@ struct ActionHistory {
 /// A pending update to deploy.
 #[derive(Debug)]
 pub struct Update {
-    client: Client,
+    client: HttpClient,
     info: Reply,
     url: String,
+    cancellation: CancellationToken,
 }
 
 impl Update {
-    fn new(client: Client, info: Reply, url: String) -> Self {
-        Self { client, info, url }
+    fn new(client: HttpClient, info: Reply, url: String) -> Self {
+        Self {
+            client,
+            info,
+            url,
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    pub(crate) fn restore(
+        client: HttpClient,
+        id: String,
+        url: String,
+        deployment: Deployment,
+    ) -> Self {
+        let info = Reply {
+            id,
+            deployment,
+            action_history: None,
+        };
+        Self::new(client, info, url)
+    }
+
+    /// A snapshot of the update which can be persisted and restored later,
+    /// for example after a reboot, see [`ActionState`].
+    pub fn state(&self, phase: ActionPhase) -> ActionState {
+        ActionState::new(
+            self.info.id.clone(),
+            self.url.clone(),
+            self.info.deployment.clone(),
+            phase,
+        )
     }
 
     /// The action id of the current update.
@@ -248,6 +407,14 @@ impl Update {
         &self.info.id
     }
 
+    /// Token cancelled when the server requested the cancellation of the update,
+    /// see [`Update::download_or_cancel`] and [`Agent`](crate::ddi::Agent).
+    ///
+    /// All the downloads of the update are interrupted once it is cancelled.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
     /// Handling for the download part of the provisioning process.
     pub fn download_type(&self) -> Type {
         self.info.deployment.download
@@ -266,23 +433,94 @@ impl Update {
     /// An iterator on all the software chunks of the update.
     pub fn chunks(&self) -> impl Iterator<Item = Chunk<'_>> {
         let client = self.client.clone();
+        let action = ActionRef {
+            id: &self.info.id,
+            url: &self.url,
+            cancellation: &self.cancellation,
+        };
 
         self.info
             .deployment
             .chunks
             .iter()
-            .map(move |c| Chunk::new(c, client.clone()))
+            .map(move |c| Chunk::new(c, client.clone(), Some(action)))
     }
 
     /// Download all software chunks to the directory defined in `dir`.
+    ///
+    /// The artifacts of each chunk are stored in a sub-directory, see [`DownloadLayout`](crate::ddi::DownloadLayout).
     pub async fn download(&self, dir: &Path) -> Result<Vec<DownloadedArtifact>, Error> {
-        let mut result = Vec::new();
-        for c in self.chunks() {
-            let downloaded = c.download(dir).await?;
-            result.extend(downloaded);
+        self.download_with(dir, &DownloadOptions::default()).await
+    }
+
+    /// Download all software chunks to the directory defined in `dir` using `options`.
+    ///
+    /// The progress reported by [`DownloadOptions::progress_feedback`] covers all the artifacts of the update.
+    pub async fn download_with(
+        &self,
+        dir: &Path,
+        options: &DownloadOptions,
+    ) -> Result<Vec<DownloadedArtifact>, Error> {
+        let size = self
+            .chunks()
+            .flat_map(|c| c.chunk.artifacts.iter())
+            .map(|a| a.size)
+            .sum();
+        let action = ActionRef {
+            id: &self.info.id,
+            url: &self.url,
+            cancellation: &self.cancellation,
+        };
+        let tracker = Tracker::new(options, self.client.clone(), Some(action), size);
+
+        let chunks: Vec<Chunk> = self.chunks().collect();
+        let mut artifacts = Vec::new();
+        for c in chunks.iter() {
+            for a in c.artifacts() {
+                artifacts.push((dir.join(options.artifact_path(c, &a)?), a));
+            }
         }
 
-        Ok(result)
+        download_artifacts(artifacts, &tracker).await
+    }
+
+    /// Download all software chunks to the directory defined in `dir` using `options`,
+    /// polling the server using `client` meanwhile in case it requests the cancellation of the update.
+    ///
+    /// If the cancellation of the update is requested, the download is interrupted, its partial
+    /// files are removed, the cancellation is confirmed to the server using [`Execution::Canceled`]
+    /// and [`Error::Canceled`] is returned.
+    /// If the download completed or failed for another reason in the meantime, the cancellation
+    /// is left unanswered: the caller can still accept it, or refuse it once the installation
+    /// has started. The [`Update::cancellation_token`] is then cancelled and the cancel action
+    /// is reported again by the next poll.
+    pub async fn download_or_cancel(
+        &self,
+        client: &Client,
+        dir: &Path,
+        options: &DownloadOptions,
+    ) -> Result<Vec<DownloadedArtifact>, Error> {
+        let policy = PollingPolicy::default();
+        let download = Box::pin(self.download_with(dir, options));
+        let watch = Box::pin(wait_for_cancel(
+            client,
+            self.action_id(),
+            Duration::ZERO,
+            &policy,
+        ));
+
+        let (result, cancel_action) = match future::select(download, watch).await {
+            Either::Left((result, _)) => (result, None),
+            Either::Right((cancel_action, download)) => {
+                self.cancellation.cancel();
+                (download.await, Some(cancel_action))
+            }
+        };
+
+        if let (Some(cancel_action), Err(Error::Canceled)) = (cancel_action, &result) {
+            cancel_action.conclude(true).await?;
+        }
+        result
     }
 
     /// Send feedback to server about this update, with custom progress information.
@@ -337,12 +575,21 @@ impl Update {
 #[derive(Debug)]
 pub struct Chunk<'a> {
     chunk: &'a ChunkInternal,
-    client: Client,
+    client: HttpClient,
+    action: Option<ActionRef<'a>>,
 }
 
 impl<'a> Chunk<'a> {
-    pub(crate) fn new(chunk: &'a ChunkInternal, client: Client) -> Self {
-        Self { chunk, client }
+    pub(crate) fn new(
+        chunk: &'a ChunkInternal,
+        client: HttpClient,
+        action: Option<ActionRef<'a>>,
+    ) -> Self {
+        Self {
+            chunk,
+            client,
+            action,
+        }
     }
 
     /// Type of the chunk.
@@ -367,7 +614,7 @@ impl<'a> Chunk<'a> {
         self.chunk
             .artifacts
             .iter()
-            .map(move |a| Artifact::new(a, client.clone()))
+            .map(move |a| Artifact::new(a, self.chunk, client.clone(), self.action))
     }
 
     /// An iterator on all the metadata of the chunk.
@@ -379,30 +626,100 @@ impl<'a> Chunk<'a> {
     }
 
     /// Download all artifacts of the chunk to the directory defined in `dir`.
+    ///
+    /// The artifacts are stored in a sub-directory, see [`DownloadLayout`](crate::ddi::DownloadLayout).
     pub async fn download(&'a self, dir: &Path) -> Result<Vec<DownloadedArtifact>, Error> {
-        let mut dir = dir.to_path_buf();
-        dir.push(self.name());
-        let mut result = Vec::new();
+        self.download_with(dir, &DownloadOptions::default()).await
+    }
+
+    /// Download all artifacts of the chunk to the directory defined in `dir` using `options`.
+    pub async fn download_with(
+        &'a self,
+        dir: &Path,
+        options: &DownloadOptions,
+    ) -> Result<Vec<DownloadedArtifact>, Error> {
+        let size = self.chunk.artifacts.iter().map(|a| a.size).sum();
+        let tracker = Tracker::new(options, self.client.clone(), self.action, size);
 
+        self.download_tracked(dir, &tracker).await
+    }
+
+    async fn download_tracked(
+        &self,
+        dir: &Path,
+        tracker: &Tracker<'_>,
+    ) -> Result<Vec<DownloadedArtifact>, Error> {
+        let mut artifacts = Vec::new();
         for a in self.artifacts() {
-            let downloaded = a.download(&dir).await?;
-            result.push(downloaded);
+            let path = dir.join(tracker.options().artifact_path(self, &a)?);
+            artifacts.push((path, a));
         }
 
-        Ok(result)
+        download_artifacts(artifacts, tracker).await
     }
 }
 
+/// Download each artifact to its path, with the concurrency defined in the options of `tracker`.
+///
+/// The results are returned in the same order as `artifacts`. If a download fails,
+/// the other ones are interrupted, keeping their `.part` file so they can be resumed.
+async fn download_artifacts(
+    artifacts: Vec<(PathBuf, Artifact<'_>)>,
+    tracker: &Tracker<'_>,
+) -> Result<Vec<DownloadedArtifact>, Error> {
+    // artifacts downloaded to the same file must not be written concurrently
+    let mut locks: HashMap<PathBuf, Arc<Mutex<()>>> = HashMap::new();
+    let paths: Vec<PathBuf> = artifacts.iter().map(|(path, _)| path.clone()).collect();
+    let mut downloads = Vec::with_capacity(artifacts.len());
+    for (i, (path, a)) in artifacts.into_iter().enumerate() {
+        let lock = locks.entry(path.clone()).or_default().clone();
+        downloads.push(async move {
+            let _guard = lock.lock().await;
+            a.download_tracked(&path, tracker).await.map(|d| (i, d))
+        });
+    }
+
+    // results are collected as they complete so a failure interrupts the other downloads immediately
+    let downloads = stream::iter(downloads)
+        .buffer_unordered(tracker.options().max_concurrency())
+        .try_collect();
+    let mut result: Vec<(usize, DownloadedArtifact)> = match tracker.cancellable(downloads).await {
+        Err(Error::Canceled) => {
+            // cancelled downloads are not resumed
+            for path in paths.iter() {
+                remove_part_file(path).await?;
+            }
+            return Err(Error::Canceled);
+        }
+        result => result?,
+    };
+
+    result.sort_by_key(|(i, _)| *i);
+    Ok(result.into_iter().map(|(_, d)| d).collect())
+}
+
 /// A single file part of a [`Chunk`] to download.
 #[derive(Debug)]
 pub struct Artifact<'a> {
     artifact: &'a ArtifactInternal,
-    client: Client,
+    chunk: &'a ChunkInternal,
+    client: HttpClient,
+    action: Option<ActionRef<'a>>,
 }
 
 impl<'a> Artifact<'a> {
-    fn new(artifact: &'a ArtifactInternal, client: Client) -> Self {
-        Self { artifact, client }
+    fn new(
+        artifact: &'a ArtifactInternal,
+        chunk: &'a ChunkInternal,
+        client: HttpClient,
+        action: Option<ActionRef<'a>>,
+    ) -> Self {
+        Self {
+            artifact,
+            chunk,
+            client,
+            action,
+        }
     }
 
     /// The name of the file.
@@ -411,47 +728,151 @@ impl<'a> Artifact<'a> {
     }
 
     /// The size of the file.
-    pub fn size(&self) -> u32 {
+    pub fn size(&self) -> u64 {
         self.artifact.size
     }
 
-    async fn download_response(&'a self) -> Result<Response, Error> {
-        let download = self
-            .artifact
-            .links
-            .https
-            .as_ref()
-            .or(self.artifact.links.http.as_ref())
-            .expect("Missing content link in for artifact");
+    /// The checksums of the file.
+    pub fn hashes(&self) -> &Hashes {
+        &self.artifact.hashes
+    }
 
-        let resp = self.client.get(download.content.to_string()).send().await?;
+    /// Type of the chunk the artifact is part of.
+    pub fn part(&self) -> &str {
+        &self.chunk.part
+    }
 
-        resp.error_for_status_ref()?;
-        Ok(resp)
+    /// Name of the chunk the artifact is part of.
+    pub fn name(&self) -> &str {
+        &self.chunk.name
     }
 
-    async fn download_response_range(&'a self, offset: u64) -> Result<Response, Error> {
-        let download = self
-            .artifact
-            .links
-            .https
+    /// Software version of the chunk the artifact is part of.
+    pub fn version(&self) -> &str {
+        &self.chunk.version
+    }
+
+    /// The URLs the artifact can be downloaded from, in order of preference.
+    fn download_urls(&self) -> Result<Vec<String>, Error> {
+        self.urls(|d| Some(&d.content))
+    }
+
+    /// The URLs of the MD5SUM file of the artifact, in order of preference.
+    fn md5sum_urls(&self) -> Result<Vec<String>, Error> {
+        self.urls(|d| d.md5sum.as_ref())
+    }
+
+    /// The `link` of the usable transports, in order of preference.
+    fn urls(&self, link: impl Fn(&Download) -> Option<&Link>) -> Result<Vec<String>, Error> {
+        let links = &self.artifact.links;
+        let https = links.https.as_ref().and_then(&link);
+        let http = links
+            .http
             .as_ref()
-            .or(self.artifact.links.http.as_ref())
-            .expect("Missing content link in for artifact");
+            .filter(|_| self.client.allows_http())
+            .and_then(&link);
 
-        let resp = self
-            .client
-            .get(download.content.to_string())
-            .header(RANGE, format!("bytes={offset}-"))
-            .send()
-            .await?;
+        let urls: Vec<String> = match self.client.transport_preference() {
+            TransportPreference::PreferHttp => vec![http, https],
+            _ => vec![https, http],
+        }
+        .into_iter()
+        .flatten()
+        .map(|l| l.to_string())
+        .collect();
 
-        resp.error_for_status_ref()?;
-        Ok(resp)
+        if urls.is_empty() {
+            Err(Error::MissingDownloadLink)
+        } else {
+            Ok(urls)
+        }
+    }
+
+    /// Download and parse the MD5SUM file of the artifact, served by the server
+    /// independently of the deployment.
+    pub async fn fetch_md5sum_file(&self) -> Result<Md5Sum, Error> {
+        let resp = download_request_retrying(&self.client, &self.md5sum_urls()?).await?;
+        resp.text().await?.parse()
+    }
+
+    /// Check that the md5sum in the MD5SUM file of the artifact matches the one in the deployment.
+    ///
+    /// This is done automatically before downloading the artifact if enabled using
+    /// [`ClientBuilder::check_md5sum_file`](crate::ddi::ClientBuilder::check_md5sum_file).
+    pub async fn check_md5sum_file(&self) -> Result<(), Error> {
+        let md5sum = self.fetch_md5sum_file().await?;
+        if md5sum.md5().eq_ignore_ascii_case(&self.artifact.hashes.md5) {
+            Ok(())
+        } else {
+            Err(Error::Md5SumMismatch {
+                deployment: self.artifact.hashes.md5.clone(),
+                md5sum: md5sum.md5,
+            })
+        }
+    }
+
+    async fn download_response(&'a self) -> Result<Response, Error> {
+        download_request(&self.client, &self.download_urls()?, 0).await
+    }
+
+    async fn download_response_range(&'a self, offset: u64) -> Result<Response, Error> {
+        download_request(&self.client, &self.download_urls()?, offset).await
     }
 
     /// Download the artifact file to the directory defined in `dir`.
+    ///
+    /// The data is first written to a `.part` file which is only renamed once it has been
+    /// flushed to disk and its size and hashes have been checked. If the hashes do not
+    /// match, the `.part` file is removed.
     pub async fn download(&'a self, dir: &Path) -> Result<DownloadedArtifact, Error> {
+        self.download_with(dir, &DownloadOptions::default()).await
+    }
+
+    /// Download the artifact file to the directory defined in `dir` using `options`.
+    pub async fn download_with(
+        &'a self,
+        dir: &Path,
+        options: &DownloadOptions,
+    ) -> Result<DownloadedArtifact, Error> {
+        let tracker = Tracker::new(
+            options,
+            self.client.clone(),
+            self.action,
+            self.artifact.size,
+        );
+
+        let filename = options.name_policy().apply(self.filename())?;
+        let path = dir.join(&*filename);
+        match tracker
+            .cancellable(self.download_tracked(&path, &tracker))
+            .await
+        {
+            Err(Error::Canceled) => {
+                remove_part_file(&path).await?;
+                Err(Error::Canceled)
+            }
+            result => result,
+        }
+    }
+
+    /// Download the artifact to the file at `file_name`.
+    async fn download_tracked(
+        &self,
+        file_name: &Path,
+        tracker: &Tracker<'_>,
+    ) -> Result<DownloadedArtifact, Error> {
+        let dir = file_name.parent().unwrap_or_else(|| Path::new(""));
+        if self.client.checks_md5sum_file() {
+            self.check_md5sum_file().await?;
+        }
+        let mut progress = tracker.artifact(
+            self.filename(),
+            &self.chunk.part,
+            &self.chunk.name,
+            &self.chunk.version,
+            self.artifact.size,
+        );
+
         if !dir.exists() {
             DirBuilder::new().recursive(true).create(dir).await?;
         }
@@ -459,22 +880,22 @@ impl<'a> Artifact<'a> {
         // Check if the file is already there (e.g. downloaded in a previous try)
         // and seems to be the file we are expecting.
         // In this case we can use this file directly and skip the download.
-        #[cfg(feature = "hash-sha256")]
+        // The file can only be trusted if its checksums are verified.
+        let policy = self.client.verification_policy();
+        if !Verifier::new(policy, &self.artifact.hashes).is_empty()
+            && tokio::fs::try_exists(file_name).await?
         {
-            let mut file_name = dir.to_path_buf();
-            file_name.push(self.filename());
-            if tokio::fs::try_exists(&file_name).await? {
-                // lets check if the files size matches our expectation
-                let metadata = tokio::fs::metadata(&file_name).await?;
-                if file_size(&metadata) == self.artifact.size as u64 {
-                    // lets check if the file hash matches our expectation
-                    let artifact = DownloadedArtifact::new(file_name, self.artifact.hashes.clone());
-                    if artifact.check_sha256().await.is_ok() {
-                        // filename, size and hash are as expected.
-                        // so we we can assume that the existant file, is the file given in the deployment
-                        // so we can skip the download and use the file from cache
-                        return Ok(artifact);
-                    }
+            // lets check if the files size matches our expectation
+            let metadata = tokio::fs::metadata(file_name).await?;
+            if file_size(&metadata) == self.artifact.size {
+                // lets check if the file hash matches our expectation
+                let artifact = self.downloaded(file_name.to_path_buf());
+                if artifact.verify(policy).await.is_ok() {
+                    // filename, size and hash are as expected.
+                    // so we we can assume that the existant file, is the file given in the deployment
+                    // so we can skip the download and use the file from cache
+                    progress.restart(self.artifact.size).await;
+                    return Ok(artifact);
                 }
             }
         }
@@ -482,41 +903,125 @@ impl<'a> Artifact<'a> {
         // the file is first downloaded to a .part file in order to
         // be able to resume the download in case of a disconnection.
         // If a part file already exists, we try to resume the download (if supported by the server).
-        let mut file_name_part = dir.to_path_buf();
-        file_name_part.push(format!("{}.part", self.filename()));
+        let file_name_part = part_file(file_name);
+
+        // the device may have stopped before renaming a complete .part file,
+        // nothing is left to download if it is valid.
+        let mut complete = false;
+        if tokio::fs::try_exists(&file_name_part).await? {
+            let size = file_size(&tokio::fs::metadata(&file_name_part).await?);
+            if size >= self.artifact.size {
+                let part = self.downloaded(file_name_part.clone());
+                complete = size == self.artifact.size && part.verify(policy).await.is_ok();
+                if complete {
+                    progress.restart(self.artifact.size).await;
+                } else {
+                    tokio::fs::remove_file(&file_name_part).await?;
+                }
+            }
+        }
 
-        let mut resp = if tokio::fs::try_exists(&file_name_part).await? {
-            // try to resume the download
-            let metadata = tokio::fs::metadata(&file_name_part).await?;
-            self.download_response_range(file_size(&metadata)).await?
-        } else {
-            self.download_response().await?
-        };
+        let limiter = tracker.options().effective_rate_limiter(&self.client);
+        let start = Instant::now();
+        let mut attempt = 1;
 
-        let mut dest = if resp.status() == reqwest::StatusCode::PARTIAL_CONTENT {
-            // the server supports range requests, we can resume the download
-            OpenOptions::new()
-                .append(true)
-                .open(&file_name_part)
-                .await?
-        } else {
-            File::create(&file_name_part).await?
-        };
+        loop {
+            if complete {
+                break;
+            }
+            let offset = if tokio::fs::try_exists(&file_name_part).await? {
+                let metadata = tokio::fs::metadata(&file_name_part).await?;
+                Some(file_size(&metadata))
+            } else {
+                None
+            };
+            let transfer: Result<(), Error> = async {
+                let mut resp = match offset {
+                    // try to resume the download
+                    Some(offset) => self.download_response_range(offset).await?,
+                    None => self.download_response().await?,
+                };
+
+                let mut dest = if resp.status() == StatusCode::PARTIAL_CONTENT {
+                    // the server supports range requests, we can resume the download
+                    progress.restart(offset.unwrap_or_default()).await;
+                    OpenOptions::new()
+                        .append(true)
+                        .open(&file_name_part)
+                        .await?
+                } else {
+                    progress.restart(0).await;
+                    File::create(&file_name_part).await?
+                };
+
+                let written: Result<(), Error> = async {
+                    while let Some(chunk) = resp.chunk().await? {
+                        if let Some(limiter) = &limiter {
+                            limiter.acquire(chunk.len() as u64).await;
+                        }
+                        dest.write_all(&chunk).await?;
+                        progress.advance(chunk.len() as u64).await;
+                    }
+                    Ok(())
+                }
+                .await;
+                dest.flush().await?;
+                written
+            }
+            .await;
+
+            match transfer {
+                Ok(_) => break,
+                Err(e)
+                    if offset.is_some()
+                        && e.status() == Some(StatusCode::RANGE_NOT_SATISFIABLE) =>
+                {
+                    // the download cannot be resumed from this offset, restart from scratch
+                    tokio::fs::remove_file(&file_name_part).await?;
+                }
+                // the server may not be reachable or the connection may have been lost
+                // during the transfer, resume the download from the data written so far.
+                Err(err) => retry_delay(&self.client, err, &mut attempt, start).await?,
+            }
+        }
 
-        while let Some(chunk) = resp.chunk().await? {
-            dest.write_all(&chunk).await?;
+        let received = file_size(&tokio::fs::metadata(&file_name_part).await?);
+        if received != self.artifact.size {
+            if received > self.artifact.size {
+                // the download cannot be resumed from a file larger than expected
+                tokio::fs::remove_file(&file_name_part).await?;
+            }
+            return Err(Error::SizeMismatch {
+                expected: self.artifact.size,
+                received,
+            });
         }
 
-        let mut file_name = dir.to_path_buf();
-        file_name.push(self.filename());
+        // make sure the data is on disk and valid before giving the file its final name,
+        // so a file with this name can always be trusted even after a power loss.
+        File::open(&file_name_part).await?.sync_all().await?;
+        let part = self.downloaded(file_name_part);
+        if !complete {
+            if let Err(e) = part.verify(policy).await {
+                // resuming from corrupted data would not help, restart from scratch next time
+                tokio::fs::remove_file(part.file()).await?;
+                return Err(e);
+            }
+        }
 
         // rename the file to remove the .part extension after the download is complete
-        tokio::fs::rename(&file_name_part, &file_name).await?;
+        tokio::fs::rename(part.file(), file_name).await?;
+        sync_dir(dir).await?;
 
-        Ok(DownloadedArtifact::new(
-            file_name,
-            self.artifact.hashes.clone(),
-        ))
+        Ok(self.downloaded(file_name.to_path_buf()))
+    }
+
+    fn downloaded(&self, file: PathBuf) -> DownloadedArtifact {
+        DownloadedArtifact::new(file, self.artifact.hashes.clone()).chunk(
+            &self.chunk.part,
+            &self.chunk.name,
+            &self.chunk.version,
+        )
     }
 
     /// Provide a `Stream` of `Bytes` to download the artifact.
@@ -524,222 +1029,306 @@ impl<'a> Artifact<'a> {
     /// This can be used as an alternative to [`Artifact::download`],
     /// for example, to extract an archive while it's being downloaded,
     /// saving the need to store the archive file on disk.
+    ///
+    /// If the connection is lost, the stream transparently reconnects according to the
+    /// [`RetryPolicy`](crate::ddi::RetryPolicy) of the client and resumes the transfer
+    /// using an HTTP Range request. If the server does not support Range requests, the data
+    /// already received is downloaded again but skipped.
+    ///
+    /// The stream raises an error at the end if the size of the downloaded data
+    /// does not match the size announced by the server, or if its checksums do not
+    /// match according to the [`VerificationPolicy`] of the client.
+    /// Note that the data is yielded before being verified, so it should not be used
+    /// until the end of the stream has been reached without error.
     pub async fn download_stream(
         &'a self,
     ) -> Result<impl Stream<Item = Result<Bytes, Error>>, Error> {
-        let resp = self.download_response().await?;
-
-        Ok(resp.bytes_stream().map_err(|e| e.into()))
+        self.download_stream_with(&DownloadOptions::default()).await
     }
 
-    /// Provide a `Stream` of `Bytes` to download the artifact while checking md5 checksum.
+    /// Provide a `Stream` of `Bytes` to download the artifact using `options`.
     ///
-    /// The stream will yield the same data as [`Artifact::download_stream`] but will raise
-    /// an error if the md5sum of the downloaded data does not match the one provided by the server.
-    #[cfg(feature = "hash-md5")]
-    pub async fn download_stream_with_md5_check(
+    /// Same as [`Artifact::download_stream`] but using the rate limiter of `options`.
+    /// Progress is not reported when downloading using a stream.
+    pub async fn download_stream_with(
         &'a self,
+        options: &DownloadOptions,
     ) -> Result<impl Stream<Item = Result<Bytes, Error>>, Error> {
-        let stream = self.download_stream().await?;
-        let hasher = DownloadHasher::new_md5(self.artifact.hashes.md5.clone());
-
-        let stream = DownloadStreamHash {
-            stream: Box::new(stream),
-            hasher,
-        };
-
-        Ok(stream)
+        self.stream(options, self.client.verification_policy())
+            .await
     }
 
-    /// Provide a `Stream` of `Bytes` to download the artifact while checking sha1 checksum.
+    /// Provide a `Stream` of `Bytes` to download the artifact, verifying its checksums using `policy`.
     ///
-    /// The stream will yield the same data as [`Artifact::download_stream`] but will raise
-    /// an error if the sha1sum of the downloaded data does not match the one provided by the server.
-    #[cfg(feature = "hash-sha1")]
-    pub async fn download_stream_with_sha1_check(
+    /// Same as [`Artifact::download_stream`] but using `policy` instead of the
+    /// [`VerificationPolicy`] of the client.
+    pub async fn download_stream_verified(
         &'a self,
+        policy: VerificationPolicy,
     ) -> Result<impl Stream<Item = Result<Bytes, Error>>, Error> {
-        let stream = self.download_stream().await?;
-        let hasher = DownloadHasher::new_sha1(self.artifact.hashes.sha1.clone());
+        self.stream(&DownloadOptions::default(), &policy).await
+    }
 
-        let stream = DownloadStreamHash {
-            stream: Box::new(stream),
-            hasher,
+    async fn stream(
+        &self,
+        options: &DownloadOptions,
+        policy: &VerificationPolicy,
+    ) -> Result<impl Stream<Item = Result<Bytes, Error>>, Error> {
+        if self.client.checks_md5sum_file() {
+            self.check_md5sum_file().await?;
+        }
+        let urls = self.download_urls()?;
+        let resp = download_request_retrying(&self.client, &urls).await?;
+
+        let state = StreamState {
+            client: self.client.clone(),
+            urls,
+            resp: Some(resp),
+            limiter: options.effective_rate_limiter(&self.client),
+            expected: self.artifact.size,
+            received: 0,
+            verifier: Some(Verifier::new(policy, &self.artifact.hashes)),
+            skip: 0,
+            attempt: 1,
+            start: Instant::now(),
         };
 
-        Ok(stream)
+        let stream = stream::try_unfold(state, |mut state| async move {
+            let data = state.next().await?;
+            Ok(data.map(|data| (data, state)))
+        });
+
+        Ok(Box::pin(stream))
     }
 
-    /// Provide a `Stream` of `Bytes` to download the artifact while checking sha256 checksum.
+    /// Download the artifact into `sink`, such as a block device or an in-memory buffer.
     ///
-    /// The stream will yield the same data as [`Artifact::download_stream`] but will raise
-    /// an error if the sha256sum of the downloaded data does not match the one provided by the server.
-    #[cfg(feature = "hash-sha256")]
-    pub async fn download_stream_with_sha256_check(
+    /// The data is checked while being written: the size announced by the server and the
+    /// checksums selected by the [`VerificationPolicy`] of the client are verified, and success
+    /// is only reported once the last byte has been written and verified.
+    /// Note that the data is written into `sink` before being verified, so it should not
+    /// be used if an error is returned.
+    ///
+    /// The transfer is resumed if the connection is lost, see [`Artifact::download_stream`].
+    ///
+    /// Return the number of bytes written.
+    pub async fn download_to<W: AsyncWrite + Unpin + ?Sized>(
         &'a self,
-    ) -> Result<impl Stream<Item = Result<Bytes, Error>>, Error> {
-        let stream = self.download_stream().await?;
-        let hasher = DownloadHasher::new_sha256(self.artifact.hashes.sha256.clone());
+        sink: &mut W,
+    ) -> Result<u64, Error> {
+        self.download_to_with(sink, &DownloadOptions::default())
+            .await
+    }
 
-        let stream = DownloadStreamHash {
-            stream: Box::new(stream),
-            hasher,
-        };
+    /// Download the artifact into `sink` using `options`.
+    ///
+    /// Same as [`Artifact::download_to`] but using the rate limiter of `options`.
+    pub async fn download_to_with<W: AsyncWrite + Unpin + ?Sized>(
+        &'a self,
+        sink: &mut W,
+        options: &DownloadOptions,
+    ) -> Result<u64, Error> {
+        // the stream checks the size and the checksums of the downloaded data
+        let mut stream = self.download_stream_with(options).await?;
+
+        let mut written = 0;
+        while let Some(data) = stream.try_next().await? {
+            sink.write_all(&data).await?;
+            written += data.len() as u64;
+        }
+        sink.flush().await?;
 
-        Ok(stream)
+        Ok(written)
     }
 }
 
-/// A downloaded file part of a [`Chunk`].
-#[allow(dead_code)]
-#[derive(Debug)]
-pub struct DownloadedArtifact {
-    file: PathBuf,
-    hashes: Hashes,
-}
-
-cfg_if::cfg_if! {
-    if #[cfg(feature = "hash-digest")] {
-        use std::{
-            pin::Pin,
-            task::Poll,
+/// Request the download of the artifact, starting from byte `offset`.
+///
+/// Each of `urls` is tried once, in order, until one succeeds. If all of them fail,
+/// the error of the first one is returned. The request is not retried here: the
+/// callers retry it along with the transfer, which can be resumed.
+async fn download_request(
+    client: &HttpClient,
+    urls: &[String],
+    offset: u64,
+) -> Result<Response, Error> {
+    let mut error = None;
+
+    for url in urls {
+        let request = if offset > 0 {
+            client.get(url).header(RANGE, format!("bytes={offset}-"))
+        } else {
+            client.get(url)
         };
-        use digest::Digest;
-
-        const HASH_BUFFER_SIZE: usize = 4096;
-
-        /// Enum representing the different type of supported checksums
-        #[derive(Debug, strum::Display, Clone)]
-        pub enum ChecksumType {
-            /// md5
-            #[cfg(feature = "hash-md5")]
-            Md5,
-            /// sha1
-            #[cfg(feature = "hash-sha1")]
-            Sha1,
-            /// sha256
-            #[cfg(feature = "hash-sha256")]
-            Sha256,
-        }
-
-        // quite complex trait bounds because of requirements so LowerHex is implemented on the output
-        #[derive(Clone)]
-        struct DownloadHasher<T>
-        where
-            T: Digest,
-            <T as digest::OutputSizeUser>::OutputSize: core::ops::Add,
-            <<T as digest::OutputSizeUser>::OutputSize as core::ops::Add>::Output: digest::generic_array::ArrayLength<u8>
-        {
-            hasher: T,
-            expected: String,
-            error: ChecksumType,
-        }
 
-        impl<T> DownloadHasher<T>
-        where
-            T: Digest,
-            <T as digest::OutputSizeUser>::OutputSize: core::ops::Add,
-            <<T as digest::OutputSizeUser>::OutputSize as core::ops::Add>::Output: digest::generic_array::ArrayLength<u8>
+        match client
+            .send_once(request.build()?, Endpoint::ArtifactDownload)
+            .await
         {
-            fn update(&mut self, data: impl AsRef<[u8]>) {
-                self.hasher.update(data);
-            }
-
-            fn finalize(self) -> Result<(), Error> {
-                let digest = self.hasher.finalize();
-
-                if format!("{:x}", digest) == self.expected {
-                    Ok(())
-                } else {
-                    Err(Error::ChecksumError(self.error))
-                }
+            Ok(resp) => return Ok(resp),
+            Err(e) => {
+                error.get_or_insert(e);
             }
         }
+    }
 
-        #[cfg(feature = "hash-md5")]
-        impl DownloadHasher<md5::Md5> {
-            fn new_md5(expected: String) -> Self {
-                Self {
-                    hasher: md5::Md5::new(),
-                    expected,
-                    error: ChecksumType::Md5,
-                }
-            }
-        }
+    Err(error.unwrap_or(Error::MissingDownloadLink))
+}
 
-        #[cfg(feature = "hash-sha1")]
-        impl DownloadHasher<sha1::Sha1> {
-            fn new_sha1(expected: String) -> Self {
-                Self {
-                    hasher: sha1::Sha1::new(),
-                    expected,
-                    error: ChecksumType::Sha1,
-                }
-            }
+/// Request the download of the whole file from `urls`, retrying according to the
+/// [`RetryPolicy`](crate::ddi::RetryPolicy) of the client.
+async fn download_request_retrying(
+    client: &HttpClient,
+    urls: &[String],
+) -> Result<Response, Error> {
+    let start = Instant::now();
+    let mut attempt = 1;
+
+    loop {
+        match download_request(client, urls, 0).await {
+            Ok(resp) => return Ok(resp),
+            Err(err) => retry_delay(client, err, &mut attempt, start).await?,
         }
+    }
+}
 
-        #[cfg(feature = "hash-sha256")]
-        impl DownloadHasher<sha2::Sha256> {
-            fn new_sha256(expected: String) -> Self {
-                Self {
-                    hasher: sha2::Sha256::new(),
-                    expected,
-                    error: ChecksumType::Sha256,
-                }
-            }
+/// Wait before the next `attempt` to download a file after `err`,
+/// or return it if the [`RetryPolicy`](crate::ddi::RetryPolicy) gives up.
+async fn retry_delay(
+    client: &HttpClient,
+    err: Error,
+    attempt: &mut u32,
+    start: Instant,
+) -> Result<(), Error> {
+    match client.retry_policy().delay(
+        &err,
+        *attempt,
+        start.elapsed(),
+        Endpoint::ArtifactDownload,
+        true,
+    ) {
+        Some(delay) => {
+            tokio::time::sleep(delay).await;
+            *attempt += 1;
+            Ok(())
         }
+        None => Err(err),
+    }
+}
 
-        struct DownloadStreamHash<T>
-        where
-            T: Digest,
-            <T as digest::OutputSizeUser>::OutputSize: core::ops::Add,
-            <<T as digest::OutputSizeUser>::OutputSize as core::ops::Add>::Output: digest::generic_array::ArrayLength<u8>
-        {
-            stream: Box<dyn Stream<Item = Result<Bytes, Error>> + Unpin + Send + Sync>,
-            hasher: DownloadHasher<T>,
-        }
+/// State of a resumable download stream.
+struct StreamState {
+    client: HttpClient,
+    urls: Vec<String>,
+    resp: Option<Response>,
+    limiter: Option<RateLimiter>,
+    expected: u64,
+    received: u64,
+    /// Verify the checksums of the data, taken once the end of the download is reached
+    verifier: Option<Verifier>,
+    /// Number of bytes to skip because they have already been received before reconnecting
+    skip: u64,
+    attempt: u32,
+    start: Instant,
+}
 
-        impl<T> Stream for DownloadStreamHash<T>
-        where
-            T: Digest,
-            <T as digest::OutputSizeUser>::OutputSize: core::ops::Add,
-            <<T as digest::OutputSizeUser>::OutputSize as core::ops::Add>::Output: digest::generic_array::ArrayLength<u8>,
-            T: Unpin,
-            T: Clone,
-        {
-            type Item = Result<Bytes, Error>;
-
-            fn poll_next(
-                self: std::pin::Pin<&mut Self>,
-                cx: &mut std::task::Context<'_>,
-            ) -> std::task::Poll<Option<Self::Item>> {
-                let me = Pin::into_inner(self);
-
-                match Pin::new(&mut me.stream).poll_next(cx) {
-                    Poll::Ready(Some(Ok(data))) => {
-                        // feed data to the hasher and then pass them back to the stream
-                        me.hasher.update(&data);
-                        Poll::Ready(Some(Ok(data)))
+impl StreamState {
+    /// The next data of the download, reconnecting to the server if the connection is lost.
+    async fn next(&mut self) -> Result<Option<Bytes>, Error> {
+        loop {
+            let resp = match &mut self.resp {
+                Some(resp) => resp,
+                None => match download_request(&self.client, &self.urls, self.received).await {
+                    Ok(resp) => {
+                        // the server may ignore the Range header and send the whole file again
+                        self.skip = if resp.status() == StatusCode::PARTIAL_CONTENT {
+                            0
+                        } else {
+                            self.received
+                        };
+                        self.resp.insert(resp)
                     }
-                    Poll::Ready(None) => {
-                        // download is done, check the hash
-                        match me.hasher.clone().finalize() {
-                            Ok(_) => Poll::Ready(None),
-                            Err(e) => Poll::Ready(Some(Err(e))),
+                    Err(err) => {
+                        retry_delay(&self.client, err, &mut self.attempt, self.start).await?;
+                        continue;
+                    }
+                },
+            };
+
+            let err = match resp.chunk().await {
+                Ok(Some(mut data)) => {
+                    if self.skip > 0 {
+                        let skipped = self.skip.min(data.len() as u64);
+                        self.skip -= skipped;
+                        data = data.slice(skipped as usize..);
+                        if data.is_empty() {
+                            continue;
                         }
                     }
-                    // passthrough on errors and pendings
-                    Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
-                    Poll::Pending => Poll::Pending,
+
+                    if let Some(limiter) = &self.limiter {
+                        limiter.acquire(data.len() as u64).await;
+                    }
+                    if let Some(verifier) = &mut self.verifier {
+                        verifier.update(&data);
+                    }
+                    self.received += data.len() as u64;
+                    return Ok(Some(data));
                 }
-            }
+                Ok(None) if self.received == self.expected => {
+                    return match self.verifier.take() {
+                        Some(verifier) => verifier.finalize().map(|_| None),
+                        None => Ok(None),
+                    };
+                }
+                Ok(None) => {
+                    return Err(Error::SizeMismatch {
+                        expected: self.expected,
+                        received: self.received,
+                    })
+                }
+                Err(err) => err.into(),
+            };
+
+            // the connection has been lost, reconnect and resume the transfer
+            self.resp = None;
+            retry_delay(&self.client, err, &mut self.attempt, self.start).await?;
         }
     }
 }
 
+/// A downloaded file part of a [`Chunk`].
+#[derive(Debug, Clone)]
+pub struct DownloadedArtifact {
+    file: PathBuf,
+    hashes: Hashes,
+    part: String,
+    name: String,
+    version: String,
+}
+
 impl DownloadedArtifact {
-    fn new(file: PathBuf, hashes: Hashes) -> Self {
-        Self { file, hashes }
+    /// Create a downloaded artifact from the file at `file` and its expected checksums,
+    /// for example to check it again after the device restarted.
+    ///
+    /// The identity of its chunk is empty, unless set using [`DownloadedArtifact::chunk`].
+    pub fn new(file: PathBuf, hashes: Hashes) -> Self {
+        Self {
+            file,
+            hashes,
+            part: String::new(),
+            name: String::new(),
+            version: String::new(),
+        }
+    }
+
+    /// Set the type, name and software version of the chunk the artifact is part of.
+    pub fn chunk(self, part: &str, name: &str, version: &str) -> Self {
+        let mut artifact = self;
+        artifact.part = part.to_string();
+        artifact.name = name.to_string();
+        artifact.version = version.to_string();
+        artifact
     }
 
     /// Path of the downloaded file.
@@ -747,16 +1336,35 @@ impl DownloadedArtifact {
         &self.file
     }
 
-    #[cfg(feature = "hash-digest")]
-    async fn hash<T>(&self, mut hasher: DownloadHasher<T>) -> Result<(), Error>
-    where
-        T: digest::Digest,
-        <T as digest::OutputSizeUser>::OutputSize: core::ops::Add,
-        <<T as digest::OutputSizeUser>::OutputSize as core::ops::Add>::Output:
-            digest::generic_array::ArrayLength<u8>,
-    {
+    /// The expected checksums of the file.
+    pub fn hashes(&self) -> &Hashes {
+        &self.hashes
+    }
+
+    /// Type of the chunk the artifact is part of.
+    pub fn part(&self) -> &str {
+        &self.part
+    }
+
+    /// Name of the chunk the artifact is part of.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Software version of the chunk the artifact is part of.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Check the checksums of the downloaded file selected by `policy`.
+    pub async fn verify(&self, policy: &VerificationPolicy) -> Result<(), Error> {
         use tokio::io::AsyncReadExt;
 
+        let mut verifier = Verifier::new(policy, &self.hashes);
+        if verifier.is_empty() {
+            return Ok(());
+        }
+
         let mut file = File::open(&self.file).await?;
         let mut buffer = [0; HASH_BUFFER_SIZE];
 
@@ -765,30 +1373,59 @@ impl DownloadedArtifact {
             if n == 0 {
                 break;
             }
-            hasher.update(&buffer[..n]);
+            verifier.update(&buffer[..n]);
         }
 
-        hasher.finalize()
+        verifier.finalize()
     }
 
     /// Check if the md5sum of the downloaded file matches the one provided by the server.
     #[cfg(feature = "hash-md5")]
     pub async fn check_md5(&self) -> Result<(), Error> {
-        let hasher = DownloadHasher::new_md5(self.hashes.md5.clone());
-        self.hash(hasher).await
+        self.verify(&VerificationPolicy::Require(ChecksumType::Md5))
+            .await
     }
 
     /// Check if the sha1sum of the downloaded file matches the one provided by the server.
     #[cfg(feature = "hash-sha1")]
     pub async fn check_sha1(&self) -> Result<(), Error> {
-        let hasher = DownloadHasher::new_sha1(self.hashes.sha1.clone());
-        self.hash(hasher).await
+        self.verify(&VerificationPolicy::Require(ChecksumType::Sha1))
+            .await
     }
 
     /// Check if the sha256sum of the downloaded file matches the one provided by the server.
     #[cfg(feature = "hash-sha256")]
     pub async fn check_sha256(&self) -> Result<(), Error> {
-        let hasher = DownloadHasher::new_sha256(self.hashes.sha256.clone());
-        self.hash(hasher).await
+        self.verify(&VerificationPolicy::Require(ChecksumType::Sha256))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_md5sum() {
+        let md5sum: Md5Sum = "5eb63bbbe01eeed093cb22bb8f5acdc3  test.txt\n"
+            .parse()
+            .unwrap();
+        assert_eq!(md5sum.md5(), "5eb63bbbe01eeed093cb22bb8f5acdc3");
+        assert_eq!(md5sum.filename(), Some("test.txt"));
+
+        let md5sum: Md5Sum = "5EB63BBBE01EEED093CB22BB8F5ACDC3 *my file.bin"
+            .parse()
+            .unwrap();
+        assert_eq!(md5sum.md5(), "5eb63bbbe01eeed093cb22bb8f5acdc3");
+        assert_eq!(md5sum.filename(), Some("my file.bin"));
+
+        let md5sum: Md5Sum = "5eb63bbbe01eeed093cb22bb8f5acdc3".parse().unwrap();
+        assert_eq!(md5sum.filename(), None);
+
+        assert!(matches!("".parse::<Md5Sum>(), Err(Error::InvalidMd5Sum)));
+        assert!(matches!(
+            "not-a-hash  test.txt".parse::<Md5Sum>(),
+            Err(Error::InvalidMd5Sum)
+        ));
     }
 }
@@ -0,0 +1,627 @@
+// Copyright 2025, Liebherr Digital Development Center GmbH.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// Options and progress reporting of artifact downloads
+
+use std::borrow::Cow;
+use std::fmt;
+use std::future::Future;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::future::{self, Either};
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+
+use crate::ddi::client::{Error, HttpClient};
+use crate::ddi::common::{send_progress_feedback, Execution};
+use crate::ddi::deployment_base::{Artifact, Chunk};
+use crate::ddi::rate_limit::RateLimiter;
+
+/// Progress of a download, reported to a [`DownloadObserver`].
+#[derive(Debug, Clone)]
+pub struct DownloadProgress {
+    filename: String,
+    part: String,
+    name: String,
+    version: String,
+    downloaded: u64,
+    size: u64,
+    overall_downloaded: u64,
+    overall_size: u64,
+    rate: u64,
+}
+
+impl DownloadProgress {
+    /// The name of the artifact file being downloaded.
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    /// The type of the chunk the artifact is part of.
+    pub fn part(&self) -> &str {
+        &self.part
+    }
+
+    /// The name of the chunk the artifact is part of.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The software version of the chunk the artifact is part of.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// The number of bytes of the artifact downloaded so far.
+    pub fn downloaded(&self) -> u64 {
+        self.downloaded
+    }
+
+    /// The size of the artifact, as announced by the server.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The number of bytes downloaded so far, for all the artifacts of the download operation.
+    pub fn overall_downloaded(&self) -> u64 {
+        self.overall_downloaded
+    }
+
+    /// The size of all the artifacts of the download operation,
+    /// such as all the artifacts of an update when using [`Update::download_with`](crate::ddi::Update::download_with).
+    pub fn overall_size(&self) -> u64 {
+        self.overall_size
+    }
+
+    /// The average transfer rate of the artifact, in bytes per second.
+    pub fn rate(&self) -> u64 {
+        self.rate
+    }
+}
+
+/// Observer notified of the progress of a download.
+///
+/// This trait is implemented for closures taking a [`DownloadProgress`].
+pub trait DownloadObserver: Send + Sync {
+    /// Called each time some data has been downloaded.
+    fn on_progress(&self, progress: &DownloadProgress);
+}
+
+impl<F: Fn(&DownloadProgress) + Send + Sync> DownloadObserver for F {
+    fn on_progress(&self, progress: &DownloadProgress) {
+        self(progress)
+    }
+}
+
+/// Report the progress of the download to the server using [`Execution::Download`] feedback.
+///
+/// The progress is sent as `{"cnt": <percent>, "of": 100}`, at most once per
+/// [`ProgressFeedback::interval`] and only if it increased by at least
+/// [`ProgressFeedback::step`] percents. The completion of the download is always reported.
+///
+/// Each message is sent only once, it is neither retried nor saved in the
+/// [`FeedbackQueue`](crate::ddi::FeedbackQueue), so the download is not delayed
+/// if the server cannot be reached.
+#[derive(Debug, Clone)]
+pub struct ProgressFeedback {
+    interval: Duration,
+    step: u32,
+}
+
+impl Default for ProgressFeedback {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            step: 5,
+        }
+    }
+}
+
+impl ProgressFeedback {
+    /// Set the minimum time between two feedback messages, default to 10 seconds.
+    pub fn interval(self, interval: Duration) -> Self {
+        let mut feedback = self;
+        feedback.interval = interval;
+        feedback
+    }
+
+    /// Set the minimum progress, in percents, between two feedback messages, default to `5`.
+    pub fn step(self, step: u32) -> Self {
+        let mut feedback = self;
+        feedback.step = step;
+        feedback
+    }
+}
+
+/// Which of the links provided by the server are used to download artifacts.
+///
+/// hawkBit provides a `download` link, served over https, and a `download-http`
+/// link, served over plain http. When both are available, the second one is used
+/// if the download from the first one fails.
+///
+/// The plain http link is never used if the client has been configured using
+/// [`ClientBuilder::https_only`](crate::ddi::ClientBuilder::https_only).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TransportPreference {
+    /// Only use the https link
+    HttpsOnly,
+    /// Use the https link, falling back to the http one
+    #[default]
+    PreferHttps,
+    /// Use the http link, falling back to the https one
+    PreferHttp,
+}
+
+/// How to handle the names of files and directories, provided by the server,
+/// which are not safe to use as a single path component.
+///
+/// A name is unsafe if it is empty, is `.` or `..`, or contains a path separator
+/// (`/` or `\`), a NUL byte or any other element allowing it to escape the download
+/// directory, such as a Windows drive prefix.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FilenamePolicy {
+    /// Fail the download with [`Error::UnsafeFilename`]
+    #[default]
+    Reject,
+    /// Replace the path separators and NUL bytes by `_`, as well as the dots of names only made of dots
+    Sanitize,
+}
+
+impl FilenamePolicy {
+    /// Return the name to use on disk for `name`.
+    pub(crate) fn apply<'a>(&self, name: &'a str) -> Result<Cow<'a, str>, Error> {
+        if is_safe_filename(name) {
+            return Ok(Cow::Borrowed(name));
+        }
+
+        match self {
+            Self::Reject => Err(Error::UnsafeFilename(name.to_string())),
+            Self::Sanitize => {
+                let mut sanitized: String = name
+                    .chars()
+                    .map(|c| match c {
+                        '/' | '\\' | '\0' => '_',
+                        c => c,
+                    })
+                    .collect();
+                if sanitized.chars().all(|c| c == '.') {
+                    sanitized = sanitized.replace('.', "_");
+                }
+                if sanitized.is_empty() {
+                    sanitized.push('_');
+                }
+
+                if is_safe_filename(&sanitized) {
+                    Ok(Cow::Owned(sanitized))
+                } else {
+                    Err(Error::UnsafeFilename(name.to_string()))
+                }
+            }
+        }
+    }
+}
+
+fn is_safe_filename(name: &str) -> bool {
+    if name.contains(['/', '\\', '\0']) {
+        return false;
+    }
+
+    // reject anything which is not a single plain component, such as `..` or a drive prefix
+    let mut components = Path::new(name).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(n)), None) if n == name
+    )
+}
+
+/// Function returning the path of an artifact, used by [`DownloadLayout::Custom`].
+pub type ArtifactPathMapper = dyn Fn(&Chunk<'_>, &Artifact<'_>) -> PathBuf + Send + Sync;
+
+/// Where the artifacts are stored, relative to the directory passed to
+/// [`Update::download_with`](crate::ddi::Update::download_with) or
+/// [`Chunk::download_with`](crate::ddi::Chunk::download_with).
+///
+/// The names provided by the server are checked according to the [`FilenamePolicy`] of the download.
+#[derive(Clone, Default)]
+pub enum DownloadLayout {
+    /// `<dir>/<name>/<filename>`, `name` being the name of the chunk
+    #[default]
+    ChunkName,
+    /// `<dir>/<part>/<name>-<version>/<filename>`, preventing collisions between
+    /// chunks having the same name
+    ChunkVersion,
+    /// The path returned by the function, which must be relative and must not contain `..`
+    Custom(Arc<ArtifactPathMapper>),
+}
+
+impl fmt::Debug for DownloadLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ChunkName => write!(f, "ChunkName"),
+            Self::ChunkVersion => write!(f, "ChunkVersion"),
+            Self::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+/// Options used to download artifacts.
+///
+/// # Examples
+///
+/// ```
+/// use hawkbit::ddi::{DownloadOptions, DownloadProgress, ProgressFeedback};
+///
+/// let options = DownloadOptions::default()
+///     .progress(|progress: &DownloadProgress| {
+///         println!("{}: {}/{}", progress.filename(), progress.downloaded(), progress.size());
+///     })
+///     .progress_feedback(ProgressFeedback::default());
+/// ```
+#[derive(Clone)]
+pub struct DownloadOptions {
+    observer: Option<Arc<dyn DownloadObserver>>,
+    feedback: Option<ProgressFeedback>,
+    rate_limiter: Option<RateLimiter>,
+    concurrency: usize,
+    filename_policy: FilenamePolicy,
+    layout: DownloadLayout,
+    cancellation: Option<CancellationToken>,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            observer: None,
+            feedback: None,
+            rate_limiter: None,
+            concurrency: 1,
+            filename_policy: FilenamePolicy::default(),
+            layout: DownloadLayout::default(),
+            cancellation: None,
+        }
+    }
+}
+
+impl fmt::Debug for DownloadOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DownloadOptions")
+            .field("observer", &self.observer.is_some())
+            .field("feedback", &self.feedback)
+            .field("rate_limiter", &self.rate_limiter)
+            .field("concurrency", &self.concurrency)
+            .field("filename_policy", &self.filename_policy)
+            .field("layout", &self.layout)
+            .field("cancellation", &self.cancellation)
+            .finish()
+    }
+}
+
+impl DownloadOptions {
+    /// Notify `observer` of the progress of the download.
+    pub fn progress<O: DownloadObserver + 'static>(self, observer: O) -> Self {
+        let mut options = self;
+        options.observer = Some(Arc::new(observer));
+        options
+    }
+
+    /// Report the progress of the download to the server, see [`ProgressFeedback`].
+    ///
+    /// Feedback is only sent when downloading artifacts of an [`Update`](crate::ddi::Update).
+    pub fn progress_feedback(self, feedback: ProgressFeedback) -> Self {
+        let mut options = self;
+        options.feedback = Some(feedback);
+        options
+    }
+
+    /// Limit the bandwidth used by the download, see [`RateLimiter`].
+    ///
+    /// Replaces the limiter of the client set using
+    /// [`ClientBuilder::rate_limiter`](crate::ddi::ClientBuilder::rate_limiter), if any.
+    pub fn rate_limiter(self, limiter: RateLimiter) -> Self {
+        let mut options = self;
+        options.rate_limiter = Some(limiter);
+        options
+    }
+
+    /// Set the maximum number of artifacts downloaded in parallel, default to `1`.
+    ///
+    /// This is used when downloading all the artifacts of an [`Update`](crate::ddi::Update)
+    /// or of a [`Chunk`](crate::ddi::Chunk). If a download fails, the other ones are
+    /// interrupted but their `.part` file is kept so they can be resumed later.
+    pub fn concurrency(self, concurrency: usize) -> Self {
+        let mut options = self;
+        options.concurrency = concurrency.max(1);
+        options
+    }
+
+    /// Set how unsafe artifact file names and chunk names are handled,
+    /// default to [`FilenamePolicy::Reject`].
+    pub fn filename_policy(self, policy: FilenamePolicy) -> Self {
+        let mut options = self;
+        options.filename_policy = policy;
+        options
+    }
+
+    /// Set where the artifacts are stored, default to [`DownloadLayout::ChunkName`].
+    pub fn layout(self, layout: DownloadLayout) -> Self {
+        let mut options = self;
+        options.layout = layout;
+        options
+    }
+
+    /// Interrupt the download when `token` is cancelled.
+    ///
+    /// The download then fails with [`Error::Canceled`] and the `.part` files of the
+    /// artifacts which have not been completely downloaded are removed.
+    pub fn cancellation_token(self, token: CancellationToken) -> Self {
+        let mut options = self;
+        options.cancellation = Some(token);
+        options
+    }
+
+    pub(crate) fn name_policy(&self) -> FilenamePolicy {
+        self.filename_policy
+    }
+
+    /// The path of `artifact` relative to the download directory.
+    pub(crate) fn artifact_path(
+        &self,
+        chunk: &Chunk<'_>,
+        artifact: &Artifact<'_>,
+    ) -> Result<PathBuf, Error> {
+        let policy = self.filename_policy;
+        let filename = policy.apply(artifact.filename())?;
+
+        let path = match &self.layout {
+            DownloadLayout::ChunkName => Path::new(&*policy.apply(chunk.name())?).join(&*filename),
+            DownloadLayout::ChunkVersion => {
+                let dir = format!("{}-{}", chunk.name(), chunk.version());
+                Path::new(&*policy.apply(chunk.part())?)
+                    .join(&*policy.apply(&dir)?)
+                    .join(&*filename)
+            }
+            DownloadLayout::Custom(mapper) => {
+                let path = mapper(chunk, artifact);
+                let safe = path.components().count() > 0
+                    && path.components().all(|c| matches!(c, Component::Normal(_)));
+                if !safe {
+                    return Err(Error::UnsafeFilename(path.to_string_lossy().into_owned()));
+                }
+                path
+            }
+        };
+
+        Ok(path)
+    }
+
+    pub(crate) fn max_concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// The limiter to use for a download with `client`.
+    pub(crate) fn effective_rate_limiter(&self, client: &HttpClient) -> Option<RateLimiter> {
+        self.rate_limiter
+            .as_ref()
+            .or(client.rate_limiter())
+            .cloned()
+    }
+}
+
+/// The action an artifact is downloaded for, used to send progress feedback.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ActionRef<'a> {
+    pub(crate) id: &'a str,
+    pub(crate) url: &'a str,
+    /// Cancelled when the action is cancelled, interrupting its downloads
+    pub(crate) cancellation: &'a CancellationToken,
+}
+
+#[derive(Debug, Serialize)]
+struct FeedbackProgress {
+    cnt: u32,
+    of: u32,
+}
+
+#[derive(Debug, Default)]
+struct TrackerState {
+    downloaded: u64,
+    feedback_sent: Option<(Instant, u32)>,
+}
+
+/// Progress of a download operation, which may include several artifacts.
+pub(crate) struct Tracker<'a> {
+    options: &'a DownloadOptions,
+    client: HttpClient,
+    action: Option<ActionRef<'a>>,
+    size: u64,
+    state: Mutex<TrackerState>,
+}
+
+impl<'a> Tracker<'a> {
+    /// The options of the download.
+    pub(crate) fn options(&self) -> &DownloadOptions {
+        self.options
+    }
+
+    pub(crate) fn new(
+        options: &'a DownloadOptions,
+        client: HttpClient,
+        action: Option<ActionRef<'a>>,
+        size: u64,
+    ) -> Self {
+        Self {
+            options,
+            client,
+            action,
+            size,
+            state: Mutex::new(TrackerState::default()),
+        }
+    }
+
+    /// Run `download`, failing with [`Error::Canceled`] if the cancellation token of
+    /// the options or of the action is cancelled before it completes.
+    pub(crate) async fn cancellable<T>(
+        &self,
+        download: impl Future<Output = Result<T, Error>>,
+    ) -> Result<T, Error> {
+        fn cancelled(token: Option<&CancellationToken>) -> impl Future<Output = ()> + '_ {
+            match token {
+                Some(token) => Either::Left(token.cancelled()),
+                None => Either::Right(future::pending()),
+            }
+        }
+        let cancelled = future::select(
+            Box::pin(cancelled(self.options.cancellation.as_ref())),
+            Box::pin(cancelled(self.action.map(|a| a.cancellation))),
+        );
+
+        match future::select(Box::pin(download), cancelled).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(Error::Canceled),
+        }
+    }
+
+    /// Start tracking the download of an artifact.
+    pub(crate) fn artifact(
+        &self,
+        filename: &str,
+        part: &str,
+        name: &str,
+        version: &str,
+        size: u64,
+    ) -> ArtifactProgress<'_> {
+        ArtifactProgress {
+            tracker: self,
+            progress: DownloadProgress {
+                filename: filename.to_string(),
+                part: part.to_string(),
+                name: name.to_string(),
+                version: version.to_string(),
+                downloaded: 0,
+                size,
+                overall_downloaded: 0,
+                overall_size: self.size,
+                rate: 0,
+            },
+            start: Instant::now(),
+            start_offset: 0,
+        }
+    }
+
+    /// Account for `added` bytes being downloaded and `removed` bytes being discarded.
+    ///
+    /// Return the overall number of downloaded bytes and the progress to report to the server, if any.
+    fn update(&self, added: u64, removed: u64) -> (u64, Option<u32>) {
+        let mut state = self.state.lock().unwrap();
+        state.downloaded = (state.downloaded + added).saturating_sub(removed);
+
+        let feedback = match (&self.options.feedback, self.action) {
+            (Some(feedback), Some(_)) => feedback,
+            _ => return (state.downloaded, None),
+        };
+
+        let percent = match self.size {
+            0 => 100,
+            size => (state.downloaded.min(size) * 100 / size) as u32,
+        };
+        let send = match state.feedback_sent {
+            None => true,
+            Some((_, sent)) if percent == 100 => sent != 100,
+            Some((at, sent)) => {
+                percent >= sent + feedback.step.max(1) && at.elapsed() >= feedback.interval
+            }
+        };
+
+        if send {
+            state.feedback_sent = Some((Instant::now(), percent));
+            (state.downloaded, Some(percent))
+        } else {
+            (state.downloaded, None)
+        }
+    }
+}
+
+/// Progress of the download of a single artifact.
+pub(crate) struct ArtifactProgress<'a> {
+    tracker: &'a Tracker<'a>,
+    progress: DownloadProgress,
+    start: Instant,
+    start_offset: u64,
+}
+
+impl ArtifactProgress<'_> {
+    /// `len` more bytes have been downloaded.
+    pub(crate) async fn advance(&mut self, len: u64) {
+        self.report(self.progress.downloaded + len).await;
+    }
+
+    /// The download (re)starts with `downloaded` bytes already available,
+    /// for example when resuming a download.
+    pub(crate) async fn restart(&mut self, downloaded: u64) {
+        self.start = Instant::now();
+        self.start_offset = downloaded;
+        self.report(downloaded).await;
+    }
+
+    async fn report(&mut self, downloaded: u64) {
+        let (overall, feedback) = self.tracker.update(downloaded, self.progress.downloaded);
+        let elapsed = self.start.elapsed().as_secs_f64();
+
+        self.progress.downloaded = downloaded;
+        self.progress.overall_downloaded = overall;
+        self.progress.rate = if elapsed > 0.0 {
+            (downloaded.saturating_sub(self.start_offset) as f64 / elapsed) as u64
+        } else {
+            0
+        };
+
+        if let Some(observer) = &self.tracker.options.observer {
+            observer.on_progress(&self.progress);
+        }
+
+        if let (Some(percent), Some(action)) = (feedback, self.tracker.action) {
+            let details = format!("Downloading {}", self.progress.filename);
+            // failing to report the progress should not interrupt the download
+            let _ = send_progress_feedback(
+                &self.tracker.client,
+                action.url,
+                action.id,
+                Execution::Download,
+                FeedbackProgress {
+                    cnt: percent,
+                    of: 100,
+                },
+                vec![&details],
+            )
+            .await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filename_policy() {
+        let reject = FilenamePolicy::Reject;
+        assert_eq!(reject.apply("app.bin").unwrap(), "app.bin");
+        assert_eq!(reject.apply("..app.bin").unwrap(), "..app.bin");
+        for name in &["", ".", "..", "../app.bin", "/etc/shadow", "a\\b", "a\0b"] {
+            assert!(matches!(
+                reject.apply(name),
+                Err(Error::UnsafeFilename(n)) if n == *name
+            ));
+        }
+
+        let sanitize = FilenamePolicy::Sanitize;
+        assert_eq!(sanitize.apply("app.bin").unwrap(), "app.bin");
+        assert_eq!(sanitize.apply("").unwrap(), "_");
+        assert_eq!(sanitize.apply("..").unwrap(), "__");
+        assert_eq!(sanitize.apply("../app.bin").unwrap(), ".._app.bin");
+        assert_eq!(sanitize.apply("/etc/shadow").unwrap(), "_etc_shadow");
+        assert_eq!(sanitize.apply("a\\b\0c").unwrap(), "a_b_c");
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright 2025, Liebherr Digital Development Center GmbH.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// Stream of events produced by regularly polling the server
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use futures::stream::{self, Stream};
+
+use crate::ddi::cancel_action::CancelAction;
+use crate::ddi::client::{Client, Error};
+use crate::ddi::config_data::ConfigRequest;
+use crate::ddi::confirmation_base::ConfirmationRequest;
+use crate::ddi::deployment_base::UpdatePreFetch;
+use crate::ddi::poll::{LinkHrefs, Reply};
+
+/// Sleeping time used if the server did not provide a valid polling interval.
+const DEFAULT_POLLING_SLEEP: Duration = Duration::from_secs(60);
+
+/// Event produced by the stream returned by [`Client::events`].
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum DdiEvent {
+    /// The server requested the device configuration.
+    ConfigRequested(ConfigRequest),
+    /// An update is available.
+    UpdateAvailable(UpdatePreFetch),
+    /// The server is waiting for the device to confirm an update.
+    ConfirmationRequired(ConfirmationRequest),
+    /// The server asked the device to cancel an action.
+    CancelRequested(CancelAction),
+    /// The server changed the suggested interval between two polls.
+    PollingIntervalChanged(Duration),
+}
+
+struct State {
+    client: Client,
+    hrefs: LinkHrefs,
+    polling_sleep: Option<Duration>,
+    pending: VecDeque<DdiEvent>,
+    first: bool,
+}
+
+impl State {
+    fn process(&mut self, reply: Reply) -> Result<(), Error> {
+        let hrefs = reply.hrefs();
+
+        if hrefs.config_data.is_some() && hrefs.config_data != self.hrefs.config_data {
+            if let Some(request) = reply.config_data_request() {
+                self.pending.push_back(DdiEvent::ConfigRequested(request));
+            }
+        }
+        if hrefs.confirmation_base.is_some()
+            && hrefs.confirmation_base != self.hrefs.confirmation_base
+        {
+            if let Some(request) = reply.confirmation_base() {
+                self.pending
+                    .push_back(DdiEvent::ConfirmationRequired(request));
+            }
+        }
+        if hrefs.deployment_base.is_some() && hrefs.deployment_base != self.hrefs.deployment_base {
+            if let Some(update) = reply.update() {
+                self.pending.push_back(DdiEvent::UpdateAvailable(update));
+            }
+        }
+        if hrefs.cancel_action.is_some() && hrefs.cancel_action != self.hrefs.cancel_action {
+            if let Some(cancel_action) = reply.cancel_action() {
+                self.pending
+                    .push_back(DdiEvent::CancelRequested(cancel_action));
+            }
+        }
+        self.hrefs = hrefs;
+
+        let polling_sleep = reply.polling_sleep()?;
+        if self.polling_sleep != Some(polling_sleep) {
+            self.polling_sleep = Some(polling_sleep);
+            self.pending
+                .push_back(DdiEvent::PollingIntervalChanged(polling_sleep));
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn events(client: Client) -> impl Stream<Item = Result<DdiEvent, Error>> {
+    let state = State {
+        client,
+        hrefs: LinkHrefs::default(),
+        polling_sleep: None,
+        pending: VecDeque::new(),
+        first: true,
+    };
+
+    stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(event) = state.pending.pop_front() {
+                return Some((Ok(event), state));
+            }
+
+            if !state.first {
+                let sleep = state.polling_sleep.unwrap_or(DEFAULT_POLLING_SLEEP);
+                tokio::time::sleep(sleep).await;
+            }
+            state.first = false;
+
+            let result = match state.client.poll().await {
+                Ok(reply) => state.process(reply),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                return Some((Err(e), state));
+            }
+        }
+    })
+}
@@ -0,0 +1,220 @@
+// Copyright 2025, Liebherr Digital Development Center GmbH.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// Durable queue of the feedback which could not be sent to the server
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::ddi::client::{Endpoint, Error, HttpClient};
+use crate::ddi::common::Finished;
+use crate::ddi::persist::write_atomic_async;
+
+/// A feedback message waiting to be sent to the server.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub(crate) struct QueuedFeedback {
+    url: String,
+    finished: Finished,
+    body: serde_json::Value,
+}
+
+impl QueuedFeedback {
+    pub(crate) fn new(url: String, finished: Finished, body: serde_json::Value) -> Self {
+        Self {
+            url,
+            finished,
+            body,
+        }
+    }
+
+    /// Whether this message can be dropped once `newer` has been queued for the same action.
+    fn superseded_by(&self, newer: &QueuedFeedback) -> bool {
+        // the final result of an action is never dropped
+        self.url == newer.url && self.finished == Finished::None
+    }
+}
+
+/// Durable queue of feedback messages, used to send them once the server can be reached again.
+///
+/// When a [`Client`](crate::ddi::Client) is configured with a queue using
+/// [`ClientBuilder::feedback_queue`](crate::ddi::ClientBuilder::feedback_queue),
+/// feedback which cannot be sent because of a transient error, such as the network
+/// being down, is saved in the queue and `send_feedback` succeeds.
+/// The queued messages are sent again, in order, before any new feedback and after
+/// each successful poll. They can also be sent explicitly using
+/// [`Client::flush_feedback`](crate::ddi::Client::flush_feedback).
+///
+/// Intermediate messages reporting the progress of an action ([`Finished::None`])
+/// are dropped when a newer message is queued for the same action, but the final
+/// result of an action is kept until the server either accepts it or rejects it
+/// with an error which is not transient. A rejected final result is then dropped,
+/// so it does not block the following messages, and its error is returned.
+///
+/// The queue is saved as JSON in a file, so messages are not lost if the device restarts.
+#[derive(Debug)]
+pub struct FeedbackQueue {
+    path: PathBuf,
+    entries: Mutex<Vec<QueuedFeedback>>,
+    // held by the task sending the queued messages
+    flushing: Mutex<()>,
+}
+
+impl FeedbackQueue {
+    /// Open the queue saved in the file at `path`, which is created if needed.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let entries = match fs::read(path) {
+            Ok(data) => serde_json::from_slice(&data)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries: Mutex::new(entries),
+            flushing: Mutex::new(()),
+        })
+    }
+
+    /// The number of messages waiting to be sent.
+    pub async fn len(&self) -> usize {
+        self.entries.lock().await.len()
+    }
+
+    /// Whether no message is waiting to be sent.
+    pub async fn is_empty(&self) -> bool {
+        self.entries.lock().await.is_empty()
+    }
+
+    /// Send `feedback`, or queue it if it cannot be sent because of a transient error.
+    ///
+    /// Previously queued messages are sent first to preserve ordering. If another task
+    /// is already sending them, `feedback` is queued and sent by that task.
+    pub(crate) async fn send(
+        &self,
+        client: &HttpClient,
+        feedback: QueuedFeedback,
+    ) -> Result<(), Error> {
+        let flushing = match self.flushing.try_lock() {
+            Ok(flushing) => flushing,
+            Err(_) => {
+                self.push(feedback).await?;
+                return self.flush_pending(client).await;
+            }
+        };
+
+        let flushed = match self.flush_entries(client).await {
+            Err(e) if e.is_retryable() => {
+                drop(flushing);
+                return self.push(feedback).await;
+            }
+            flushed => flushed,
+        };
+        match send_feedback(client, &feedback).await {
+            Ok(()) => {}
+            Err(e) if e.is_retryable() => {
+                drop(flushing);
+                return self.push(feedback).await.and(flushed);
+            }
+            Err(e) => return Err(e),
+        }
+        drop(flushing);
+
+        // other tasks may have queued messages while this one was sending
+        self.flush_pending(client).await.and(flushed)
+    }
+
+    /// Send all the queued messages, stopping at the first transient error.
+    pub(crate) async fn flush(&self, client: &HttpClient) -> Result<(), Error> {
+        let _flushing = self.flushing.lock().await;
+        self.flush_entries(client).await
+    }
+
+    /// Send the queued messages, the caller has to hold `flushing`.
+    ///
+    /// The entries are not locked while sending so new messages can be queued meanwhile.
+    /// Messages rejected by the server are dropped and the error of the first rejected
+    /// final result is returned once all the others have been sent.
+    async fn flush_entries(&self, client: &HttpClient) -> Result<(), Error> {
+        let mut rejected = None;
+
+        loop {
+            let feedback = match self.entries.lock().await.first() {
+                Some(feedback) => feedback.clone(),
+                None => break,
+            };
+
+            match send_feedback(client, &feedback).await {
+                Ok(()) => {}
+                // the final result is no longer needed
+                Err(e) if action_closed(&e) => {}
+                // the server will never accept this message
+                Err(e) if !e.is_retryable() => {
+                    if feedback.finished != Finished::None && rejected.is_none() {
+                        rejected = Some(e);
+                    }
+                }
+                // sent again later
+                Err(e) => return Err(e),
+            }
+
+            let mut entries = self.entries.lock().await;
+            // the message may have been superseded while it was being sent
+            if entries.first() == Some(&feedback) {
+                entries.remove(0);
+                self.save(&entries).await?;
+            }
+        }
+
+        rejected.map_or(Ok(()), Err)
+    }
+
+    /// Send the queued messages unless another task is already doing it.
+    async fn flush_pending(&self, client: &HttpClient) -> Result<(), Error> {
+        let mut result = Ok(());
+
+        while !self.is_empty().await {
+            let _flushing = match self.flushing.try_lock() {
+                Ok(flushing) => flushing,
+                Err(_) => break,
+            };
+            match self.flush_entries(client).await {
+                Ok(()) => {}
+                Err(e) if e.is_retryable() => break,
+                Err(e) => result = Err(e),
+            }
+        }
+
+        result
+    }
+
+    async fn push(&self, feedback: QueuedFeedback) -> Result<(), Error> {
+        let mut entries = self.entries.lock().await;
+        entries.retain(|e| !e.superseded_by(&feedback));
+        entries.push(feedback);
+        self.save(&entries).await
+    }
+
+    async fn save(&self, entries: &[QueuedFeedback]) -> Result<(), Error> {
+        write_atomic_async(self.path.clone(), serde_json::to_vec(entries)?).await
+    }
+}
+
+/// Whether `error` confirms the action has already been closed on the server.
+fn action_closed(error: &Error) -> bool {
+    matches!(error, Error::NotFound { status, .. } if *status == StatusCode::GONE)
+}
+
+async fn send_feedback(client: &HttpClient, feedback: &QueuedFeedback) -> Result<(), Error> {
+    client
+        .send(
+            client.post(&feedback.url).json(&feedback.body),
+            Endpoint::Feedback,
+        )
+        .await?;
+    Ok(())
+}
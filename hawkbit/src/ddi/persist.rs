@@ -0,0 +1,62 @@
+// Copyright 2025, Liebherr Digital Development Center GmbH.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// Saving files so they are not lost or corrupted if the device loses power
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::ddi::client::Error;
+
+/// Replace the content of the file at `path` with `data`.
+///
+/// The data is first written to a temporary file which then replaces the
+/// previous one, so the file is never partially written if the device
+/// loses power while saving. This is blocking, see [`write_atomic_async`].
+fn write_atomic(path: &Path, data: &[u8]) -> Result<(), Error> {
+    let mut tmp = path.file_name().unwrap_or_default().to_os_string();
+    tmp.push(".tmp");
+    let tmp = path.with_file_name(tmp);
+
+    {
+        let mut file = File::create(&tmp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)?;
+
+    // persist the rename
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    sync_dir_blocking(dir)
+}
+
+/// Same as [`write_atomic`] but running on a thread where blocking is acceptable.
+pub(crate) async fn write_atomic_async(path: PathBuf, data: Vec<u8>) -> Result<(), Error> {
+    tokio::task::spawn_blocking(move || write_atomic(&path, &data))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// Persist the entries of `dir`, such as a file which has just been renamed.
+pub(crate) async fn sync_dir(dir: &Path) -> Result<(), Error> {
+    // directories cannot be opened as files on other platforms
+    #[cfg(target_family = "unix")]
+    tokio::fs::File::open(dir).await?.sync_all().await?;
+    #[cfg(not(target_family = "unix"))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Same as [`sync_dir`] but blocking.
+fn sync_dir_blocking(dir: &Path) -> Result<(), Error> {
+    // directories cannot be opened as files on other platforms
+    #[cfg(target_family = "unix")]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(target_family = "unix"))]
+    let _ = dir;
+    Ok(())
+}
@@ -5,12 +5,11 @@
 
 use std::time::Duration;
 
-use reqwest::Client;
 use serde::Deserialize;
 
 use crate::ddi::cancel_action::CancelAction;
-use crate::ddi::client::Error;
-use crate::ddi::common::Link;
+use crate::ddi::client::{Error, HttpClient};
+use crate::ddi::common::{random_fraction, Link};
 use crate::ddi::config_data::ConfigRequest;
 use crate::ddi::confirmation_base::ConfirmationRequest;
 use crate::ddi::deployment_base::UpdatePreFetch;
@@ -41,15 +40,24 @@ pub struct Links {
     cancel_action: Option<Link>,
 }
 
+/// The links of a polling reply, used to detect changes between two polls.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct LinkHrefs {
+    pub(crate) config_data: Option<String>,
+    pub(crate) deployment_base: Option<String>,
+    pub(crate) confirmation_base: Option<String>,
+    pub(crate) cancel_action: Option<String>,
+}
+
 /// Polling reply from the server
 #[derive(Debug)]
 pub struct Reply {
     reply: ReplyInternal,
-    client: Client,
+    client: HttpClient,
 }
 
 impl Reply {
-    pub(crate) fn new(reply: ReplyInternal, client: Client) -> Self {
+    pub(crate) fn new(reply: ReplyInternal, client: HttpClient) -> Self {
         Self { reply, client }
     }
 
@@ -58,6 +66,23 @@ impl Reply {
         self.reply.config.polling.as_duration()
     }
 
+    /// Sleeping time suggested by the server, adjusted using `policy`.
+    pub fn polling_sleep_with(&self, policy: &PollingPolicy) -> Result<Duration, Error> {
+        Ok(policy.apply(self.polling_sleep()?))
+    }
+
+    pub(crate) fn hrefs(&self) -> LinkHrefs {
+        match &self.reply.links {
+            Some(links) => LinkHrefs {
+                config_data: links.config_data.as_ref().map(|l| l.to_string()),
+                deployment_base: links.deployment_base.as_ref().map(|l| l.to_string()),
+                confirmation_base: links.confirmation_base.as_ref().map(|l| l.to_string()),
+                cancel_action: links.cancel_action.as_ref().map(|l| l.to_string()),
+            },
+            None => LinkHrefs::default(),
+        }
+    }
+
     /// Returns pending configuration data request from the server, if any.
     pub fn config_data_request(&self) -> Option<ConfigRequest> {
         match &self.reply.links {
@@ -103,6 +128,85 @@ impl Reply {
     }
 }
 
+/// Policy adjusting the polling interval suggested by the server.
+///
+/// A random jitter can be applied to the interval so devices restarting at the same
+/// time do not keep polling the server in lockstep. The interval is then bounded by
+/// [`PollingPolicy::min_interval`] and [`PollingPolicy::max_interval`].
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use hawkbit::ddi::PollingPolicy;
+///
+/// let policy = PollingPolicy::default()
+///     .min_interval(Duration::from_secs(30))
+///     .max_interval(Duration::from_secs(3600))
+///     .jitter(0.1);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PollingPolicy {
+    min_interval: Duration,
+    max_interval: Option<Duration>,
+    jitter: f64,
+}
+
+impl Default for PollingPolicy {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::ZERO,
+            max_interval: None,
+            jitter: 0.0,
+        }
+    }
+}
+
+impl PollingPolicy {
+    /// Set the minimum interval between two polls, default to no minimum.
+    pub fn min_interval(self, interval: Duration) -> Self {
+        let mut policy = self;
+        policy.min_interval = interval;
+        policy
+    }
+
+    /// Set the maximum interval between two polls, default to no maximum.
+    pub fn max_interval(self, interval: impl Into<Option<Duration>>) -> Self {
+        let mut policy = self;
+        policy.max_interval = interval.into();
+        policy
+    }
+
+    /// Set the random jitter applied to the interval, as a fraction of it, default to `0`.
+    ///
+    /// With a jitter of `0.1`, an interval of 60 seconds is randomly picked between 54 and 66 seconds.
+    /// The value is clamped between `0` and `1`, and non-finite values disable the jitter.
+    pub fn jitter(self, jitter: f64) -> Self {
+        let mut policy = self;
+        policy.jitter = if jitter.is_finite() {
+            jitter.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        policy
+    }
+
+    /// Adjust the polling interval `sleep` according to the policy.
+    pub fn apply(&self, sleep: Duration) -> Duration {
+        let sleep = if self.jitter > 0.0 {
+            sleep.mul_f64(1.0 + self.jitter * (2.0 * random_fraction() - 1.0))
+        } else {
+            sleep
+        };
+
+        let sleep = sleep.max(self.min_interval);
+        match self.max_interval {
+            Some(max) => sleep.min(max),
+            None => sleep,
+        }
+    }
+}
+
 impl Polling {
     fn as_duration(&self) -> Result<Duration, Error> {
         let times: Vec<Result<u64, _>> = self.sleep.split(':').map(|s| s.parse()).collect();
@@ -148,4 +252,39 @@ mod tests {
         };
         assert!(polling.as_duration().is_err());
     }
+
+    #[test]
+    fn polling_policy() {
+        let sleep = Duration::from_secs(60);
+        assert_eq!(PollingPolicy::default().apply(sleep), sleep);
+
+        let policy = PollingPolicy::default()
+            .min_interval(Duration::from_secs(120))
+            .max_interval(Duration::from_secs(600));
+        assert_eq!(policy.apply(sleep), Duration::from_secs(120));
+        assert_eq!(
+            policy.apply(Duration::from_secs(3600)),
+            Duration::from_secs(600)
+        );
+        assert_eq!(
+            policy.apply(Duration::from_secs(300)),
+            Duration::from_secs(300)
+        );
+
+        let policy = PollingPolicy::default().jitter(0.1);
+        for _ in 0..100 {
+            let delay = policy.apply(sleep);
+            assert!(delay >= Duration::from_secs(54) && delay <= Duration::from_secs(66));
+        }
+
+        // bounds are applied after the jitter
+        let policy = policy.max_interval(sleep);
+        for _ in 0..100 {
+            assert!(policy.apply(sleep) <= sleep);
+        }
+
+        for jitter in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(PollingPolicy::default().jitter(jitter).apply(sleep), sleep);
+        }
+    }
 }
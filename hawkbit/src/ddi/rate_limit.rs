@@ -0,0 +1,118 @@
+// Copyright 2025, Liebherr Digital Development Center GmbH.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// Limiting the bandwidth used by downloads
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Maximum time waited before checking again if the rate has been changed.
+const MAX_WAIT: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+struct State {
+    rate: Option<u64>,
+    tokens: f64,
+    last: Instant,
+}
+
+impl State {
+    fn refill(&mut self, rate: f64) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        // allow bursts of up to one second of data
+        self.tokens = (self.tokens + elapsed * rate).min(rate);
+        self.last = now;
+    }
+}
+
+/// Limit the bandwidth used to download artifacts.
+///
+/// The limiter can be cloned and shared between downloads, which are then limited
+/// together. Its rate can be changed at any time, including while downloading.
+///
+/// A limiter can be set for all the downloads of a client using
+/// [`ClientBuilder::rate_limiter`](crate::ddi::ClientBuilder::rate_limiter),
+/// or for a single download using [`DownloadOptions::rate_limiter`](crate::ddi::DownloadOptions::rate_limiter).
+///
+/// # Examples
+///
+/// ```
+/// use hawkbit::ddi::{Client, RateLimiter};
+///
+/// // limit downloads to 1 MB/s
+/// let limiter = RateLimiter::new(1_000_000);
+/// let client = Client::builder("http://my-server.com:8080", "DEFAULT", "my-device")
+///     .rate_limiter(limiter.clone())
+///     .build()
+///     .unwrap();
+///
+/// // remove the limit
+/// limiter.set_rate(None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    state: Arc<Mutex<State>>,
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `bytes_per_second` bytes to be downloaded per second.
+    pub fn new(bytes_per_second: u64) -> Self {
+        Self::with_rate(Some(bytes_per_second))
+    }
+
+    /// Create a limiter which does not limit the downloads until its rate is set.
+    pub fn unlimited() -> Self {
+        Self::with_rate(None)
+    }
+
+    fn with_rate(rate: Option<u64>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                rate: rate.map(|r| r.max(1)),
+                tokens: 0.0,
+                last: Instant::now(),
+            })),
+        }
+    }
+
+    /// The number of bytes which can be downloaded per second, or `None` if not limited.
+    pub fn rate(&self) -> Option<u64> {
+        self.state.lock().unwrap().rate
+    }
+
+    /// Change the number of bytes which can be downloaded per second, `None` removing the limit.
+    pub fn set_rate(&self, bytes_per_second: Option<u64>) {
+        let mut state = self.state.lock().unwrap();
+        state.rate = bytes_per_second.map(|r| r.max(1));
+        state.tokens = 0.0;
+        state.last = Instant::now();
+    }
+
+    /// Wait until `len` bytes can be downloaded.
+    pub(crate) async fn acquire(&self, len: u64) {
+        let mut remaining = len as f64;
+
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let rate = match state.rate {
+                    Some(rate) => rate as f64,
+                    None => return,
+                };
+
+                state.refill(rate);
+                if state.tokens >= remaining {
+                    state.tokens -= remaining;
+                    return;
+                }
+                remaining -= state.tokens;
+                state.tokens = 0.0;
+
+                Duration::from_secs_f64(remaining / rate).min(MAX_WAIT)
+            };
+
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
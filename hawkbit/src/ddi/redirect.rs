@@ -0,0 +1,213 @@
+// Copyright 2025, Liebherr Digital Development Center GmbH.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// Following HTTP redirects without leaking credentials
+
+use reqwest::StatusCode;
+use url::{Origin, Url};
+
+use crate::ddi::client::Error;
+
+/// Policy used to follow the HTTP redirects returned by the server,
+/// for example to download artifacts from an object storage.
+///
+/// The client follows redirects itself so it can apply the following rules:
+/// - the authorization header and the headers set using
+///   [`ClientBuilder::default_header`](crate::ddi::ClientBuilder::default_header) are only sent to
+///   the origin (scheme, host and port) of the original request;
+/// - redirects from https to plain http are refused;
+/// - the number of redirects followed for a request is limited;
+/// - redirects to another origin can be restricted to a list of hosts;
+/// - the body of a request, such as feedback, is only sent to another origin
+///   if its host is in the list of allowed hosts.
+///
+/// # Examples
+///
+/// ```
+/// use hawkbit::ddi::{Client, RedirectPolicy};
+///
+/// let client = Client::builder("https://my-server.com", "DEFAULT", "my-device")
+///     .redirect_policy(
+///         RedirectPolicy::default()
+///             .max_redirects(3)
+///             .allowed_hosts(&["storage.my-server.com", "*.s3.amazonaws.com"]),
+///     )
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RedirectPolicy {
+    max_redirects: usize,
+    allowed_hosts: Option<Vec<String>>,
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self {
+            max_redirects: 10,
+            allowed_hosts: None,
+        }
+    }
+}
+
+impl RedirectPolicy {
+    /// Do not follow any redirect, requests being redirected fail with [`Error::TooManyRedirects`].
+    pub fn none() -> Self {
+        Self::default().max_redirects(0)
+    }
+
+    /// Set the maximum number of redirects followed for a request, default to `10`.
+    pub fn max_redirects(self, max_redirects: usize) -> Self {
+        let mut policy = self;
+        policy.max_redirects = max_redirects;
+        policy
+    }
+
+    /// Only follow redirects to another origin if its host is in `hosts`, default to any host.
+    ///
+    /// A host starting with `*.` matches all its sub-domains.
+    /// Requests with a body are only redirected to another origin if its host is in `hosts`.
+    pub fn allowed_hosts<S: AsRef<str>>(self, hosts: &[S]) -> Self {
+        let mut policy = self;
+        policy.allowed_hosts = Some(
+            hosts
+                .iter()
+                .map(|h| h.as_ref().to_ascii_lowercase())
+                .collect(),
+        );
+        policy
+    }
+
+    /// Check if the redirect from `from` to `to` can be followed, `hops` redirects
+    /// having already been followed for the request.
+    pub(crate) fn check(&self, from: &Url, to: &Url, hops: usize) -> Result<(), Error> {
+        if hops >= self.max_redirects {
+            return Err(Error::TooManyRedirects);
+        }
+
+        let refused = || Err(Error::RedirectRefused(to.to_string()));
+        match (from.scheme(), to.scheme()) {
+            ("https", "https") | ("http", "http") | ("http", "https") => {}
+            _ => return refused(),
+        }
+
+        if to.origin() != from.origin() && !self.host_allowed(to.host_str().unwrap_or_default()) {
+            return refused();
+        }
+
+        Ok(())
+    }
+
+    /// Check if the body of a request sent to `origin` can be sent again to `to`.
+    pub(crate) fn check_body(&self, origin: &Origin, to: &Url) -> Result<(), Error> {
+        if to.origin() == *origin
+            || (self.allowed_hosts.is_some()
+                && self.host_allowed(to.host_str().unwrap_or_default()))
+        {
+            Ok(())
+        } else {
+            Err(Error::RedirectRefused(to.to_string()))
+        }
+    }
+
+    fn host_allowed(&self, host: &str) -> bool {
+        let hosts = match &self.allowed_hosts {
+            Some(hosts) => hosts,
+            None => return true,
+        };
+
+        let host = host.to_ascii_lowercase();
+        hosts
+            .iter()
+            .any(|allowed| match allowed.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .is_some_and(|sub| sub.ends_with('.')),
+                None => *allowed == host,
+            })
+    }
+}
+
+/// Whether `status` is a redirect which can be followed.
+pub(crate) fn is_redirect(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(policy: &RedirectPolicy, from: &str, to: &str, hops: usize) -> Result<(), Error> {
+        policy.check(&from.parse().unwrap(), &to.parse().unwrap(), hops)
+    }
+
+    #[test]
+    fn redirect_policy() {
+        let policy = RedirectPolicy::default();
+        assert!(check(&policy, "https://a.com/x", "https://b.com/y", 0).is_ok());
+        assert!(check(&policy, "http://a.com/x", "https://a.com/y", 0).is_ok());
+        assert!(matches!(
+            check(&policy, "https://a.com/x", "http://a.com/y", 0),
+            Err(Error::RedirectRefused(url)) if url == "http://a.com/y"
+        ));
+        assert!(matches!(
+            check(&policy, "https://a.com/x", "ftp://a.com/y", 0),
+            Err(Error::RedirectRefused(_))
+        ));
+        assert!(check(&policy, "https://a.com/x", "https://b.com/y", 9).is_ok());
+        assert!(matches!(
+            check(&policy, "https://a.com/x", "https://b.com/y", 10),
+            Err(Error::TooManyRedirects)
+        ));
+        assert!(matches!(
+            check(
+                &RedirectPolicy::none(),
+                "https://a.com/x",
+                "https://a.com/y",
+                0
+            ),
+            Err(Error::TooManyRedirects)
+        ));
+
+        let policy = RedirectPolicy::default().allowed_hosts(&["b.com", "*.storage.com"]);
+        assert!(check(&policy, "https://a.com/x", "https://a.com/y", 0).is_ok());
+        assert!(check(&policy, "https://a.com/x", "https://B.com/y", 0).is_ok());
+        assert!(check(&policy, "https://a.com/x", "https://eu.storage.com/y", 0).is_ok());
+        for to in &[
+            "https://c.com/y",
+            "https://storage.com/y",
+            "https://evilstorage.com/y",
+            "https://a.com:8443/y",
+        ] {
+            assert!(matches!(
+                check(&policy, "https://a.com/x", to, 0),
+                Err(Error::RedirectRefused(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn redirect_body() {
+        let origin = "https://a.com/x".parse::<Url>().unwrap().origin();
+        let check_body =
+            |policy: &RedirectPolicy, to: &str| policy.check_body(&origin, &to.parse().unwrap());
+
+        let policy = RedirectPolicy::default();
+        assert!(check_body(&policy, "https://a.com/y").is_ok());
+        assert!(matches!(
+            check_body(&policy, "https://b.com/y"),
+            Err(Error::RedirectRefused(url)) if url == "https://b.com/y"
+        ));
+
+        let policy = policy.allowed_hosts(&["b.com"]);
+        assert!(check_body(&policy, "https://b.com/y").is_ok());
+        assert!(check_body(&policy, "https://a.com:8443/y").is_err());
+    }
+}
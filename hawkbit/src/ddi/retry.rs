@@ -0,0 +1,356 @@
+// Copyright 2025, Liebherr Digital Development Center GmbH.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// Retrying requests failing because of transient errors
+
+use std::time::{Duration, SystemTime};
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::Method;
+
+use crate::ddi::client::{Endpoint, Error};
+use crate::ddi::common::random_fraction;
+
+/// Policy used to retry requests failing because of transient errors.
+///
+/// Failed requests are retried using an exponential backoff: the first retry is
+/// done after [`RetryPolicy::initial_backoff`], then the delay is multiplied by
+/// [`RetryPolicy::multiplier`] for each new attempt, up to [`RetryPolicy::max_backoff`].
+///
+/// Only errors for which [`Error::is_retryable`] returns `true` are retried.
+/// Requests which are not safe to be replayed, such as confirming an update,
+/// are only retried if the server did not process them: when the connection to
+/// the server failed or if the server asked to retry later (429 or 503).
+/// Feedback is considered safe to be sent again as the server only keeps the latest status.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use hawkbit::ddi::{Client, RetryPolicy};
+///
+/// let policy = RetryPolicy::default()
+///     .max_attempts(10)
+///     .max_elapsed_time(Duration::from_secs(600));
+/// let client = Client::builder("http://my-server.com:8080", "DEFAULT", "my-device")
+///     .retry_policy(policy)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    max_elapsed_time: Option<Duration>,
+    jitter: bool,
+    respect_retry_after: bool,
+    disabled_endpoints: Vec<Endpoint>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            multiplier: 2.0,
+            max_elapsed_time: Some(Duration::from_secs(300)),
+            jitter: true,
+            respect_retry_after: true,
+            disabled_endpoints: Vec::new(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy never retrying any request.
+    pub fn none() -> Self {
+        Self::default().max_attempts(1)
+    }
+
+    /// Set the maximum number of attempts for a single request, including the first one, default to `5`.
+    pub fn max_attempts(self, max_attempts: u32) -> Self {
+        let mut policy = self;
+        policy.max_attempts = max_attempts.max(1);
+        policy
+    }
+
+    /// Set the delay before the first retry, default to 1 second.
+    pub fn initial_backoff(self, backoff: Duration) -> Self {
+        let mut policy = self;
+        policy.initial_backoff = backoff;
+        policy
+    }
+
+    /// Set the maximum delay between two attempts, default to 60 seconds.
+    pub fn max_backoff(self, backoff: Duration) -> Self {
+        let mut policy = self;
+        policy.max_backoff = backoff;
+        policy
+    }
+
+    /// Set the factor applied to the delay after each attempt, default to `2`.
+    pub fn multiplier(self, multiplier: f64) -> Self {
+        let mut policy = self;
+        policy.multiplier = multiplier.max(1.0);
+        policy
+    }
+
+    /// Set the maximum time spent retrying a request, default to 5 minutes.
+    ///
+    /// No new attempt is made if it would start after this time has elapsed
+    /// since the first attempt. `None` disables this limit.
+    pub fn max_elapsed_time(self, max_elapsed_time: impl Into<Option<Duration>>) -> Self {
+        let mut policy = self;
+        policy.max_elapsed_time = max_elapsed_time.into();
+        policy
+    }
+
+    /// Set whether a random jitter should be applied to the delays, default to `true`.
+    ///
+    /// The jitter prevents many devices from retrying at the exact same time.
+    /// Each delay is then picked randomly between half and the full backoff.
+    pub fn jitter(self, jitter: bool) -> Self {
+        let mut policy = self;
+        policy.jitter = jitter;
+        policy
+    }
+
+    /// Set whether the delay requested by the server in the `Retry-After` header
+    /// should be used instead of the backoff, default to `true`.
+    pub fn respect_retry_after(self, respect: bool) -> Self {
+        let mut policy = self;
+        policy.respect_retry_after = respect;
+        policy
+    }
+
+    /// Set whether requests sent to `endpoint` should be retried, default to `true` for all endpoints.
+    pub fn retry_endpoint(self, endpoint: Endpoint, enabled: bool) -> Self {
+        let mut policy = self;
+        policy.disabled_endpoints.retain(|e| *e != endpoint);
+        if !enabled {
+            policy.disabled_endpoints.push(endpoint);
+        }
+        policy
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        let backoff = self
+            .initial_backoff
+            .mul_f64(factor.min(u32::MAX as f64))
+            .min(self.max_backoff);
+
+        if self.jitter {
+            backoff.mul_f64(0.5 + random_fraction() / 2.0)
+        } else {
+            backoff
+        }
+    }
+
+    /// The delay to wait before sending the request again after `error`, or
+    /// `None` if the request should not be retried.
+    ///
+    /// # Arguments
+    /// * `error`: the error returned by the last attempt
+    /// * `attempt`: the number of attempts done so far
+    /// * `elapsed`: the time elapsed since the first attempt
+    /// * `endpoint`: the endpoint the request was sent to
+    /// * `replay_safe`: whether the request can be sent again even if the server may have processed it
+    pub(crate) fn delay(
+        &self,
+        error: &Error,
+        attempt: u32,
+        elapsed: Duration,
+        endpoint: Endpoint,
+        replay_safe: bool,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts || self.disabled_endpoints.contains(&endpoint) {
+            return None;
+        }
+
+        let retry = if replay_safe {
+            error.is_retryable()
+        } else {
+            // only retry if we know for sure the server did not process the request
+            match error {
+                Error::ServerUnavailable { .. } => true,
+                Error::ReqwestError(e) => e.is_connect(),
+                _ => false,
+            }
+        };
+        if !retry {
+            return None;
+        }
+
+        let delay = match error {
+            Error::ServerUnavailable {
+                retry_after: Some(retry_after),
+                ..
+            } if self.respect_retry_after => *retry_after,
+            _ => self.backoff(attempt),
+        };
+
+        match self.max_elapsed_time {
+            Some(max) if elapsed + delay > max => None,
+            _ => Some(delay),
+        }
+    }
+}
+
+/// Whether a request can be sent again even if the server may already have processed it.
+pub(crate) fn is_replay_safe(method: &Method, endpoint: Endpoint) -> bool {
+    // sending the same feedback twice only updates the action with the same status
+    endpoint == Endpoint::Feedback || method.is_idempotent()
+}
+
+/// Parse the `Retry-After` header, expressed either in seconds or as an HTTP date.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    match value.parse::<u64>() {
+        Ok(secs) => Some(Duration::from_secs(secs)),
+        Err(_) => {
+            let date = httpdate::parse_http_date(value).ok()?;
+            Some(
+                date.duration_since(SystemTime::now())
+                    .unwrap_or(Duration::ZERO),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use reqwest::StatusCode;
+
+    fn unavailable(retry_after: Option<Duration>) -> Error {
+        Error::ServerUnavailable {
+            endpoint: Endpoint::Poll,
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            body: None,
+            retry_after,
+        }
+    }
+
+    #[test]
+    fn backoff() {
+        let policy = RetryPolicy::default()
+            .jitter(false)
+            .initial_backoff(Duration::from_secs(1))
+            .max_backoff(Duration::from_secs(5))
+            .max_attempts(10)
+            .max_elapsed_time(None);
+        let error = unavailable(None);
+
+        let delays: Vec<_> = (1..6)
+            .map(|attempt| policy.delay(&error, attempt, Duration::ZERO, Endpoint::Poll, true))
+            .collect();
+        assert_eq!(
+            delays,
+            vec![
+                Some(Duration::from_secs(1)),
+                Some(Duration::from_secs(2)),
+                Some(Duration::from_secs(4)),
+                Some(Duration::from_secs(5)),
+                Some(Duration::from_secs(5)),
+            ]
+        );
+
+        // jitter keeps the delay between half and the full backoff
+        let policy = policy.jitter(true);
+        for _ in 0..100 {
+            let delay = policy
+                .delay(&error, 2, Duration::ZERO, Endpoint::Poll, true)
+                .unwrap();
+            assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(2));
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let policy = RetryPolicy::default()
+            .jitter(false)
+            .max_attempts(3)
+            .max_elapsed_time(Duration::from_secs(10));
+        let error = unavailable(None);
+
+        assert!(policy
+            .delay(&error, 2, Duration::ZERO, Endpoint::Poll, true)
+            .is_some());
+        assert!(policy
+            .delay(&error, 3, Duration::ZERO, Endpoint::Poll, true)
+            .is_none());
+        assert!(policy
+            .delay(&error, 1, Duration::from_secs(10), Endpoint::Poll, true)
+            .is_none());
+        assert!(RetryPolicy::none()
+            .delay(&error, 1, Duration::ZERO, Endpoint::Poll, true)
+            .is_none());
+
+        let policy = policy.retry_endpoint(Endpoint::Poll, false);
+        assert!(policy
+            .delay(&error, 1, Duration::ZERO, Endpoint::Poll, true)
+            .is_none());
+        assert!(policy
+            .delay(&error, 1, Duration::ZERO, Endpoint::ConfigData, true)
+            .is_some());
+    }
+
+    #[test]
+    fn replay() {
+        let policy = RetryPolicy::default();
+        let error = Error::HttpStatus {
+            endpoint: Endpoint::ConfirmationBase,
+            status: StatusCode::BAD_GATEWAY,
+            body: None,
+        };
+
+        assert!(policy
+            .delay(&error, 1, Duration::ZERO, Endpoint::ConfirmationBase, true)
+            .is_some());
+        assert!(policy
+            .delay(&error, 1, Duration::ZERO, Endpoint::ConfirmationBase, false)
+            .is_none());
+        assert!(policy
+            .delay(
+                &unavailable(None),
+                1,
+                Duration::ZERO,
+                Endpoint::ConfirmationBase,
+                false
+            )
+            .is_some());
+
+        assert!(is_replay_safe(&Method::GET, Endpoint::Poll));
+        assert!(is_replay_safe(&Method::PUT, Endpoint::ConfigData));
+        assert!(is_replay_safe(&Method::POST, Endpoint::Feedback));
+        assert!(!is_replay_safe(&Method::POST, Endpoint::ConfirmationBase));
+    }
+
+    #[test]
+    fn retry_after_header() {
+        let policy = RetryPolicy::default().max_elapsed_time(None);
+        let error = unavailable(Some(Duration::from_secs(120)));
+        assert_eq!(
+            policy.delay(&error, 1, Duration::ZERO, Endpoint::Poll, true),
+            Some(Duration::from_secs(120))
+        );
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("30"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(30)));
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(retry_after(&headers), None);
+    }
+}
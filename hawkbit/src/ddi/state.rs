@@ -0,0 +1,152 @@
+// Copyright 2025, Liebherr Digital Development Center GmbH.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// Persisting the state of an action across restarts
+
+use std::fmt;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::ddi::client::{Client, Error};
+use crate::ddi::deployment_base::{Deployment, Update};
+use crate::ddi::persist::write_atomic_async;
+
+/// Progress of the device on an action.
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ActionPhase {
+    /// The update has been received but not processed yet
+    Received,
+    /// The artifacts are being downloaded
+    Downloading,
+    /// All the artifacts have been downloaded
+    Downloaded,
+    /// The update is being installed
+    Installing,
+    /// The update has been installed, for example waiting for a reboot to be applied
+    Installed,
+}
+
+/// Snapshot of an [`Update`] which can be persisted, for example to report the
+/// result of an installation after rebooting the device.
+///
+/// Use [`Update::state`] to create it and [`ActionState::into_update`] to restore the update.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ActionState {
+    #[serde(rename = "actionId")]
+    action_id: String,
+    url: String,
+    deployment: Deployment,
+    phase: ActionPhase,
+}
+
+impl ActionState {
+    pub(crate) fn new(
+        action_id: String,
+        url: String,
+        deployment: Deployment,
+        phase: ActionPhase,
+    ) -> Self {
+        Self {
+            action_id,
+            url,
+            deployment,
+            phase,
+        }
+    }
+
+    /// The id of the action.
+    pub fn action_id(&self) -> &str {
+        &self.action_id
+    }
+
+    /// The URL of the `deploymentBase` resource of the action, also used to send feedback.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The progress of the device on the action.
+    pub fn phase(&self) -> ActionPhase {
+        self.phase
+    }
+
+    /// Update the progress of the device on the action.
+    pub fn set_phase(&mut self, phase: ActionPhase) {
+        self.phase = phase;
+    }
+
+    /// Restore the update, using `client` to communicate with the server.
+    pub fn into_update(self, client: &Client) -> Update {
+        Update::restore(
+            client.http_client(),
+            self.action_id,
+            self.url,
+            self.deployment,
+        )
+    }
+}
+
+/// Storage of the [`ActionState`] of the action in progress.
+///
+/// The methods return boxed futures, like the ones of
+/// [`AuthProvider`](crate::ddi::AuthProvider), so the store can be used as a trait object.
+pub trait StateStore: fmt::Debug + Send + Sync {
+    /// Load the stored state, or `None` if no state has been saved.
+    fn load(&self) -> BoxFuture<'_, Result<Option<ActionState>, Error>>;
+
+    /// Save `state`, replacing the previous one.
+    fn save<'a>(&'a self, state: &'a ActionState) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Remove the stored state, once the action is closed.
+    fn clear(&self) -> BoxFuture<'_, Result<(), Error>>;
+}
+
+/// [`StateStore`] saving the state as JSON in a file.
+///
+/// The state is first written to a temporary file which then replaces the
+/// previous one, so the file is never partially written if the device
+/// loses power while saving.
+#[derive(Debug)]
+pub struct FileStateStore {
+    path: PathBuf,
+}
+
+impl FileStateStore {
+    /// Store the state in the file at `path`.
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl StateStore for FileStateStore {
+    fn load(&self) -> BoxFuture<'_, Result<Option<ActionState>, Error>> {
+        Box::pin(async move {
+            match tokio::fs::read(&self.path).await {
+                Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
+                Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+
+    fn save<'a>(&'a self, state: &'a ActionState) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(
+            async move { write_atomic_async(self.path.clone(), serde_json::to_vec(state)?).await },
+        )
+    }
+
+    fn clear(&self) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            match tokio::fs::remove_file(&self.path).await {
+                Ok(()) => Ok(()),
+                Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright 2025, Liebherr Digital Development Center GmbH.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// Verification of the checksums of the downloaded artifacts
+
+use crate::ddi::client::Error;
+use crate::ddi::deployment_base::Hashes;
+
+/// Which checksums of the artifacts are verified when downloading them.
+///
+/// Only the checksums whose feature (`hash-md5`, `hash-sha1` or `hash-sha256`)
+/// is enabled can be verified. The size of the artifacts is always checked.
+///
+/// The policy is set for all the downloads of a client using
+/// [`ClientBuilder::verification_policy`](crate::ddi::ClientBuilder::verification_policy).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationPolicy {
+    /// Do not verify any checksum
+    SizeOnly,
+    /// Verify the strongest checksum available: sha256, then sha1, then md5
+    Strongest,
+    /// Verify all the checksums available
+    #[default]
+    All,
+    /// Verify the given checksum
+    #[cfg(feature = "hash-digest")]
+    Require(ChecksumType),
+}
+
+/// Verify the checksums selected by a [`VerificationPolicy`] while data is being downloaded.
+#[derive(Clone)]
+pub(crate) struct Verifier {
+    #[cfg(feature = "hash-md5")]
+    md5: Option<DownloadHasher<md5::Md5>>,
+    #[cfg(feature = "hash-sha1")]
+    sha1: Option<DownloadHasher<sha1::Sha1>>,
+    #[cfg(feature = "hash-sha256")]
+    sha256: Option<DownloadHasher<sha2::Sha256>>,
+}
+
+impl Verifier {
+    #[cfg_attr(
+        not(any(feature = "hash-md5", feature = "hash-sha1", feature = "hash-sha256")),
+        allow(unused_variables)
+    )]
+    pub(crate) fn new(policy: &VerificationPolicy, hashes: &Hashes) -> Self {
+        Self {
+            #[cfg(feature = "hash-md5")]
+            md5: policy
+                .includes(ChecksumType::Md5)
+                .then(|| DownloadHasher::new_md5(hashes.md5().to_string())),
+            #[cfg(feature = "hash-sha1")]
+            sha1: policy
+                .includes(ChecksumType::Sha1)
+                .then(|| DownloadHasher::new_sha1(hashes.sha1().to_string())),
+            #[cfg(feature = "hash-sha256")]
+            sha256: policy
+                .includes(ChecksumType::Sha256)
+                .then(|| DownloadHasher::new_sha256(hashes.sha256().to_string())),
+        }
+    }
+
+    /// Whether no checksum is verified.
+    pub(crate) fn is_empty(&self) -> bool {
+        #[cfg(feature = "hash-md5")]
+        if self.md5.is_some() {
+            return false;
+        }
+        #[cfg(feature = "hash-sha1")]
+        if self.sha1.is_some() {
+            return false;
+        }
+        #[cfg(feature = "hash-sha256")]
+        if self.sha256.is_some() {
+            return false;
+        }
+        true
+    }
+
+    #[cfg_attr(
+        not(any(feature = "hash-md5", feature = "hash-sha1", feature = "hash-sha256")),
+        allow(unused_variables)
+    )]
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        #[cfg(feature = "hash-md5")]
+        if let Some(hasher) = &mut self.md5 {
+            hasher.update(data);
+        }
+        #[cfg(feature = "hash-sha1")]
+        if let Some(hasher) = &mut self.sha1 {
+            hasher.update(data);
+        }
+        #[cfg(feature = "hash-sha256")]
+        if let Some(hasher) = &mut self.sha256 {
+            hasher.update(data);
+        }
+    }
+
+    /// Check the checksums of all the data passed to [`Verifier::update`].
+    pub(crate) fn finalize(self) -> Result<(), Error> {
+        #[cfg(feature = "hash-md5")]
+        if let Some(hasher) = self.md5 {
+            hasher.finalize()?;
+        }
+        #[cfg(feature = "hash-sha1")]
+        if let Some(hasher) = self.sha1 {
+            hasher.finalize()?;
+        }
+        #[cfg(feature = "hash-sha256")]
+        if let Some(hasher) = self.sha256 {
+            hasher.finalize()?;
+        }
+        Ok(())
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "hash-digest")] {
+        use digest::Digest;
+
+        /// Enum representing the different type of supported checksums
+        #[derive(Debug, strum::Display, Clone, Copy, PartialEq, Eq)]
+        pub enum ChecksumType {
+            /// md5
+            #[cfg(feature = "hash-md5")]
+            Md5,
+            /// sha1
+            #[cfg(feature = "hash-sha1")]
+            Sha1,
+            /// sha256
+            #[cfg(feature = "hash-sha256")]
+            Sha256,
+        }
+
+        #[cfg(any(feature = "hash-md5", feature = "hash-sha1", feature = "hash-sha256"))]
+        impl VerificationPolicy {
+            /// Whether the `checksum` is verified by this policy.
+            fn includes(&self, checksum: ChecksumType) -> bool {
+                match self {
+                    Self::SizeOnly => false,
+                    Self::Strongest => strongest() == Some(checksum),
+                    Self::All => true,
+                    Self::Require(required) => checksum == *required,
+                }
+            }
+        }
+
+        /// The checksums which can be verified, from the strongest to the weakest.
+        #[cfg(any(feature = "hash-md5", feature = "hash-sha1", feature = "hash-sha256"))]
+        const CHECKSUMS: &[ChecksumType] = &[
+            #[cfg(feature = "hash-sha256")]
+            ChecksumType::Sha256,
+            #[cfg(feature = "hash-sha1")]
+            ChecksumType::Sha1,
+            #[cfg(feature = "hash-md5")]
+            ChecksumType::Md5,
+        ];
+
+        /// The strongest checksum which can be verified, if any.
+        #[cfg(any(feature = "hash-md5", feature = "hash-sha1", feature = "hash-sha256"))]
+        fn strongest() -> Option<ChecksumType> {
+            CHECKSUMS.first().copied()
+        }
+
+        // quite complex trait bounds because of requirements so LowerHex is implemented on the output
+        #[derive(Clone)]
+        struct DownloadHasher<T>
+        where
+            T: Digest,
+            <T as digest::OutputSizeUser>::OutputSize: core::ops::Add,
+            <<T as digest::OutputSizeUser>::OutputSize as core::ops::Add>::Output: digest::generic_array::ArrayLength<u8>
+        {
+            hasher: T,
+            expected: String,
+            error: ChecksumType,
+        }
+
+        impl<T> DownloadHasher<T>
+        where
+            T: Digest,
+            <T as digest::OutputSizeUser>::OutputSize: core::ops::Add,
+            <<T as digest::OutputSizeUser>::OutputSize as core::ops::Add>::Output: digest::generic_array::ArrayLength<u8>
+        {
+            fn update(&mut self, data: impl AsRef<[u8]>) {
+                self.hasher.update(data);
+            }
+
+            fn finalize(self) -> Result<(), Error> {
+                let digest = self.hasher.finalize();
+
+                if format!("{:x}", digest) == self.expected {
+                    Ok(())
+                } else {
+                    Err(Error::ChecksumError(self.error))
+                }
+            }
+        }
+
+        #[cfg(feature = "hash-md5")]
+        impl DownloadHasher<md5::Md5> {
+            fn new_md5(expected: String) -> Self {
+                Self {
+                    hasher: md5::Md5::new(),
+                    expected,
+                    error: ChecksumType::Md5,
+                }
+            }
+        }
+
+        #[cfg(feature = "hash-sha1")]
+        impl DownloadHasher<sha1::Sha1> {
+            fn new_sha1(expected: String) -> Self {
+                Self {
+                    hasher: sha1::Sha1::new(),
+                    expected,
+                    error: ChecksumType::Sha1,
+                }
+            }
+        }
+
+        #[cfg(feature = "hash-sha256")]
+        impl DownloadHasher<sha2::Sha256> {
+            fn new_sha256(expected: String) -> Self {
+                Self {
+                    hasher: sha2::Sha256::new(),
+                    expected,
+                    error: ChecksumType::Sha256,
+                }
+            }
+        }
+    }
+}
@@ -8,7 +8,8 @@ use std::{path::PathBuf, time::Duration};
 use bytes::Bytes;
 use futures::prelude::*;
 use hawkbit::ddi::{
-    Client, ConfirmationResponse, Error, Execution, Finished, MaintenanceWindow, Mode, Type,
+    Client, ConfirmationResponse, DownloadedArtifact, Error, Execution, Finished, Hashes,
+    MaintenanceWindow, Mode, Type,
 };
 use httpmock::Method::GET;
 use httpmock::{Then, When};
@@ -210,6 +211,18 @@ async fn deployment() {
         let art = chunk.artifacts().next().unwrap();
         assert_eq!(art.filename(), "test.txt");
         assert_eq!(art.size(), 11);
+        assert_eq!(art.part(), name);
+        assert_eq!(art.name(), "some-chunk");
+        assert_eq!(art.version(), "1.0");
+        assert_eq!(art.hashes().md5(), "5eb63bbbe01eeed093cb22bb8f5acdc3");
+        assert_eq!(
+            art.hashes().sha1(),
+            "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed"
+        );
+        assert_eq!(
+            art.hashes().sha256(),
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
 
         let out_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
         let artifacts = chunk
@@ -222,6 +235,8 @@ async fn deployment() {
         let p = artifacts[0].file();
         assert_eq!(p.file_name().unwrap(), "test.txt");
         assert!(p.exists());
+        assert_eq!(artifacts[0].hashes(), art.hashes());
+        assert_eq!(artifacts[0].part(), name);
 
         #[cfg(feature = "hash-md5")]
         artifacts[0].check_md5().await.expect("invalid md5");
@@ -229,26 +244,31 @@ async fn deployment() {
         artifacts[0].check_sha1().await.expect("invalid sha1");
         #[cfg(feature = "hash-sha256")]
         artifacts[0].check_sha256().await.expect("invalid sha256");
+
+        // the artifact can be restored, for example after a reboot
+        let hashes = Hashes::new(
+            art.hashes().md5(),
+            art.hashes().sha1(),
+            art.hashes().sha256(),
+        );
+        let restored = DownloadedArtifact::new(p.clone(), hashes).chunk(name, "some-chunk", "1.0");
+        assert_eq!(restored.hashes(), art.hashes());
+        assert_eq!(restored.version(), "1.0");
+        #[cfg(feature = "hash-sha256")]
+        restored.check_sha256().await.expect("invalid sha256");
     }
 }
 
-#[tokio::test]
-async fn resume_download() {
-    init();
-
-    let server = ServerBuilder::default().build();
-    let (client, target) = add_target(&server, "Target1");
-
-    let test_artifact = artifact_path();
-
+/// A deployment whose artifact `test.txt` is served by `mock`.
+fn resume_deployment(mock: Box<dyn Fn(When, Then)>) -> Deployment {
     let artifacts = vec![(
-        test_artifact,
+        artifact_path(),
         "5eb63bbbe01eeed093cb22bb8f5acdc3",
         "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed",
         "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9",
     )];
 
-    let deployment = DeploymentBuilder::new("10", Type::Forced, Type::Attempt)
+    DeploymentBuilder::new("10", Type::Forced, Type::Attempt)
         .confirmation_required(false)
         .maintenance_window(MaintenanceWindow::Available)
         .chunk_with_mock(
@@ -256,17 +276,37 @@ async fn resume_download() {
             "app-both",
             "1.0",
             "some-chunk",
-            artifacts.clone(),
-            // Modify the artifact to be a partial download
-            Box::new(|when: When, then: Then| {
-                let when = when.method(GET).path("/download/test.txt");
+            artifacts,
+            mock,
+        )
+        .build()
+}
 
-                when.header("Range", "bytes=5-");
+/// Write `data` to the `.part` file of `test.txt` downloaded to `out_dir`.
+async fn write_part_file(out_dir: &TempDir, data: &[u8]) -> PathBuf {
+    let part = out_dir.path().join("some-chunk").join("test.txt.part");
+    tokio::fs::create_dir_all(part.parent().unwrap())
+        .await
+        .unwrap();
+    tokio::fs::write(&part, data).await.unwrap();
+    part
+}
 
-                then.status(206).body(" world");
-            }),
-        )
-        .build();
+#[tokio::test]
+async fn resume_download() {
+    init();
+
+    let server = ServerBuilder::default().build();
+    let (client, target) = add_target(&server, "Target1");
+
+    // Modify the artifact to be a partial download
+    let deployment = resume_deployment(Box::new(|when: When, then: Then| {
+        let when = when.method(GET).path("/download/test.txt");
+
+        when.header("Range", "bytes=5-");
+
+        then.status(206).body(" world");
+    }));
 
     target.push_deployment(deployment);
 
@@ -295,17 +335,8 @@ async fn resume_download() {
     assert_eq!(art.size(), 11);
 
     let out_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
-    let mut partial_download_file = out_dir.path().to_path_buf();
-    partial_download_file.push("some-chunk");
-    partial_download_file.push("test.txt.part");
-    // Write a wrong prefix to the part file to be able to test whether
-    // the download is resumed or simply overwritten.
-    tokio::fs::create_dir_all(partial_download_file.parent().unwrap())
-        .await
-        .unwrap();
-    tokio::fs::write(partial_download_file, b"HELLO")
-        .await
-        .unwrap();
+    // only the missing data is served by the server
+    let partial_download_file = write_part_file(&out_dir, b"hello").await;
     let artifacts = chunk
         .download(out_dir.path())
         .await
@@ -316,373 +347,614 @@ async fn resume_download() {
     let p = artifacts[0].file();
     assert_eq!(p.file_name().unwrap(), "test.txt");
     assert!(p.exists());
-    assert_eq!(tokio::fs::read_to_string(p).await.unwrap(), "HELLO world");
+    assert!(!partial_download_file.exists());
+    assert_eq!(tokio::fs::read_to_string(p).await.unwrap(), "hello world");
+
+    #[cfg(feature = "hash-md5")]
+    artifacts[0].check_md5().await.expect("invalid md5");
+    #[cfg(feature = "hash-sha1")]
+    artifacts[0].check_sha1().await.expect("invalid sha1");
+    #[cfg(feature = "hash-sha256")]
+    artifacts[0].check_sha256().await.expect("invalid sha256");
 }
 
 #[tokio::test]
-async fn send_deployment_feedback() {
+async fn resume_corrupted_download() {
     init();
 
     let server = ServerBuilder::default().build();
-    let deploy = get_deployment(false, true);
-    let deploy_id = deploy.id.clone();
     let (client, target) = add_target(&server, "Target1");
-    target.push_deployment(deploy);
+    target.push_deployment(resume_deployment(Box::new(|when: When, then: Then| {
+        when.method(GET)
+            .path("/download/test.txt")
+            .header("Range", "bytes=5-");
+        then.status(206).body(" world");
+    })));
 
     let reply = client.poll().await.expect("poll failed");
     let update = reply.update().expect("missing update");
     let update = update.fetch().await.expect("failed to fetch update info");
+    let chunk = update.chunks().next().unwrap();
 
-    // Send feedback without progress
-    let mut mock = target.expect_deployment_feedback(
-        &deploy_id,
-        Execution::Proceeding,
-        Finished::None,
-        None,
-        vec!["Downloading"],
-    );
-    assert_eq!(mock.calls(), 0);
-
-    update
-        .send_feedback(Execution::Proceeding, Finished::None, vec!["Downloading"])
-        .await
-        .expect("Failed to send feedback");
-    assert_eq!(mock.calls(), 1);
-    mock.delete();
-
-    // Send feedback with progress
-    let mut mock = target.expect_deployment_feedback(
-        &deploy_id,
-        Execution::Closed,
-        Finished::Success,
-        Some(json!({"awesome": true})),
-        vec!["Done"],
-    );
-    assert_eq!(mock.calls(), 0);
+    // the part file does not hold a prefix of the artifact
+    let out_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    let partial_download_file = write_part_file(&out_dir, b"HELLO").await;
+    let result = chunk.download(out_dir.path()).await;
 
-    #[derive(Serialize)]
-    struct Progress {
-        awesome: bool,
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "hash-digest")] {
+            // the resumed file does not match the expected hashes and is discarded
+            assert_matches::assert_matches!(result, Err(Error::ChecksumError(_)));
+            assert!(!partial_download_file.exists());
+            assert!(!out_dir.path().join("some-chunk/test.txt").exists());
+        } else {
+            // nothing can detect the corruption
+            let artifacts = result.expect("Failed to download update");
+            let p = artifacts[0].file();
+            assert!(!partial_download_file.exists());
+            assert_eq!(tokio::fs::read_to_string(p).await.unwrap(), "HELLO world");
+        }
     }
-    let progress = Progress { awesome: true };
-
-    update
-        .send_feedback_with_progress(
-            Execution::Closed,
-            Finished::Success,
-            Some(progress),
-            vec!["Done"],
-        )
-        .await
-        .expect("Failed to send feedback");
-    assert_eq!(mock.calls(), 1);
-    mock.delete();
 }
 
 #[tokio::test]
-async fn confirmation() {
+async fn resume_complete_download() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use httpmock::HttpMockResponse;
+
     init();
 
+    // the server cannot resume the download
+    let ranges = Arc::new(AtomicUsize::new(0));
+    let range_requests = ranges.clone();
     let server = ServerBuilder::default().build();
-    let deploy = get_deployment(true, true);
-    let deploy_id = deploy.id.clone();
     let (client, target) = add_target(&server, "Target1");
-    target.push_deployment(deploy);
+    target.push_deployment(resume_deployment(Box::new(
+        move |when: When, then: Then| {
+            let range_requests = range_requests.clone();
+            when.method(GET).path("/download/test.txt");
+            then.respond_with(move |req| {
+                if req.headers().contains_key("Range") {
+                    range_requests.fetch_add(1, Ordering::SeqCst);
+                    HttpMockResponse::builder().status(416).build()
+                } else {
+                    HttpMockResponse::builder()
+                        .status(200)
+                        .body("hello world")
+                        .build()
+                }
+            });
+        },
+    )));
 
     let reply = client.poll().await.expect("poll failed");
-    let confirmation = reply
-        .confirmation_base()
-        .expect("missing confirmation request");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+    let chunk = update.chunks().next().unwrap();
+    let out_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    let file = out_dir.path().join("some-chunk").join("test.txt");
+    let download = |part: &'static [u8]| {
+        let (chunk, out_dir, file) = (&chunk, &out_dir, &file);
+        async move {
+            let _ = tokio::fs::remove_file(file).await;
+            let part = write_part_file(out_dir, part).await;
+            chunk
+                .download(out_dir.path())
+                .await
+                .expect("Failed to download update");
+            assert!(!part.exists());
+            tokio::fs::read_to_string(file).await.unwrap()
+        }
+    };
 
-    // Decline the confirmation
-    let mut mock = target.expect_confirmation_feedback(
-        &deploy_id,
-        Some(-1),
-        ConfirmationResponse::Denied,
-        vec![],
-    );
-    assert_eq!(mock.calls(), 0);
+    // the .part file was complete but not renamed yet, nothing is requested
+    assert_eq!(download(b"hello world").await, "hello world");
+    assert_eq!(ranges.load(Ordering::SeqCst), 0);
+
+    // the download restarts from scratch if the server refuses to resume it
+    assert_eq!(download(b"hello").await, "hello world");
+    let refused = ranges.load(Ordering::SeqCst);
+    assert!(refused > 0);
+
+    // a complete but corrupted .part file is downloaded again
+    #[cfg(feature = "hash-digest")]
+    assert_eq!(download(b"HELLO WORLD").await, "hello world");
+    // a file larger than expected is downloaded again
+    assert_eq!(download(b"hello world!").await, "hello world");
+    assert_eq!(ranges.load(Ordering::SeqCst), refused);
+}
 
-    confirmation
-        .decline()
-        .await
-        .expect("Failed to send feedback");
-    assert_eq!(mock.calls(), 1);
-    mock.delete();
+#[tokio::test]
+async fn artifact_size() {
+    use assert_matches::assert_matches;
 
-    let reply = client.poll().await.expect("poll failed");
-    let confirmation = reply
-        .confirmation_base()
-        .expect("missing confirmation request");
-    let update_info = confirmation
-        .update_info()
-        .await
-        .expect("failed to fetch update info");
+    init();
 
-    let action_id = update_info.action_id();
-    assert_eq!(action_id, deploy_id);
+    // announce a size larger than 4 GiB while serving the small test file
+    let size = 5 * 1024 * 1024 * 1024;
+    let deployment = DeploymentBuilder::new("10", Type::Forced, Type::Attempt)
+        .chunk(
+            ChunkProtocol::BOTH,
+            "app",
+            "1.0",
+            "some-chunk",
+            vec![(
+                artifact_path(),
+                "5eb63bbbe01eeed093cb22bb8f5acdc3",
+                "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed",
+                "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9",
+            )],
+        )
+        .artifact_size("test.txt", size)
+        .build();
 
-    assert_eq!(
-        update_info.metadata(),
-        confirmation.metadata().await.unwrap()
-    );
+    let server = ServerBuilder::default().build();
+    let (client, target) = add_target(&server, "Target1");
+    target.push_deployment(deployment);
 
-    // Accept the confirmation
-    let mut mock = target.expect_confirmation_feedback(
-        &deploy_id,
-        Some(1),
-        ConfirmationResponse::Confirmed,
-        vec![],
+    let reply = client.poll().await.expect("poll failed");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+    let chunk = update.chunks().next().unwrap();
+    let art = chunk.artifacts().next().unwrap();
+    assert_eq!(art.size(), size);
+
+    // the downloads check the announced size
+    let out_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    assert_matches!(
+        art.download(out_dir.path()).await,
+        Err(Error::SizeMismatch { expected, received: 11 }) if expected == size
     );
-    assert_eq!(mock.calls(), 0);
 
-    confirmation
-        .confirm()
+    let stream = art
+        .download_stream()
         .await
-        .expect("Failed to send feedback");
-    assert_eq!(mock.calls(), 1);
-    mock.delete();
+        .expect("failed to get download stream");
+    let result: Result<Vec<Bytes>, Error> = stream.try_collect().await;
+    assert_matches!(
+        result,
+        Err(Error::SizeMismatch { expected, received: 11 }) if expected == size
+    );
 }
 
 #[tokio::test]
-async fn confirmation_metadata() {
+async fn rate_limiter() {
+    use std::time::Instant;
+
+    use hawkbit::ddi::{DownloadOptions, RateLimiter};
+
     init();
 
     let server = ServerBuilder::default().build();
-    let deploy = get_deployment(true, true);
-    let (client, target) = add_target(&server, "Target1");
-    target.push_deployment(deploy);
+    let target = server.add_target("Target1");
+    target.push_deployment(get_deployment(false, true));
+
+    // the client limiter is used by default
+    let limiter = RateLimiter::new(10);
+    let client = Client::builder(&server.base_url(), &server.tenant, &target.name)
+        .authorization(target.client_auth.clone())
+        .rate_limiter(limiter.clone())
+        .build()
+        .expect("DDI creation failed");
 
     let reply = client.poll().await.expect("poll failed");
-    let confirmation = reply
-        .confirmation_base()
-        .expect("missing confirmation request");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+    let chunk = update.chunks().next().unwrap();
+    let art = chunk.artifacts().next().unwrap();
 
-    let metadata = confirmation
-        .metadata()
+    // the 11 bytes of the artifact need more than a second at 10 bytes/s
+    let start = Instant::now();
+    let stream = art
+        .download_stream()
         .await
-        .expect("failed to fetch metadata");
-    assert_eq!(metadata.len(), 3);
-    assert_eq!(metadata[0], ("key1".to_string(), "value1".to_string()));
-    assert_eq!(metadata[1], ("key2".to_string(), "value2".to_string()));
-    assert_eq!(metadata[2], ("key3".to_string(), "value3".to_string()));
+        .expect("failed to get download stream");
+    let data: Vec<Bytes> = stream.try_collect().await.expect("download failed");
+    assert_eq!(data.concat(), b"hello world");
+    assert!(start.elapsed() >= Duration::from_secs(1));
+
+    // the limit can be changed at runtime
+    limiter.set_rate(None);
+    let start = Instant::now();
+    let out_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    art.download(out_dir.path())
+        .await
+        .expect("Failed to download artifact");
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    // a limiter can be set for a single download
+    let options = DownloadOptions::default().rate_limiter(RateLimiter::new(10));
+    let start = Instant::now();
+    let out_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    art.download_with(out_dir.path(), &options)
+        .await
+        .expect("Failed to download artifact");
+    assert!(start.elapsed() >= Duration::from_secs(1));
 }
 
 #[tokio::test]
-async fn config_then_deploy() {
+async fn unsafe_filename() {
+    use assert_matches::assert_matches;
+    use hawkbit::ddi::{DownloadOptions, FilenamePolicy};
+
     init();
 
     let server = ServerBuilder::default().build();
     let (client, target) = add_target(&server, "Target1");
+    target.push_deployment(
+        DeploymentBuilder::new("10", Type::Forced, Type::Attempt)
+            .chunk(
+                ChunkProtocol::BOTH,
+                "app",
+                "1.0",
+                "../escape",
+                vec![(
+                    artifact_path(),
+                    "5eb63bbbe01eeed093cb22bb8f5acdc3",
+                    "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed",
+                    "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9",
+                )],
+            )
+            .build(),
+    );
 
     let reply = client.poll().await.expect("poll failed");
-    assert!(reply.config_data_request().is_none());
-    assert!(reply.update().is_none());
-
-    // server requests config
-    let expected_config_data = json!({
-        "mode" : "merge",
-        "data" : {
-            "awesome" : true,
-        },
-        "status" : {
-            "result" : {
-            "finished" : "success"
-            },
-            "execution" : "closed",
-            "details" : [ "Some stuffs" ]
-        }
-    });
-    target.request_config(expected_config_data);
-
-    let reply = client.poll().await.expect("poll failed");
-    assert!(reply.config_data_request().is_some());
-    assert!(reply.update().is_none());
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
 
-    // server pushes an update
-    target.push_deployment(get_deployment(false, true));
+    let root = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    let out_dir = root.path().join("out");
+    assert_matches!(
+        update.download(&out_dir).await,
+        Err(Error::UnsafeFilename(name)) if name == "../escape"
+    );
+    assert!(!root.path().join("escape").exists());
 
-    let reply = client.poll().await.expect("poll failed");
-    assert!(reply.config_data_request().is_some());
-    assert!(reply.update().is_some());
+    let options = DownloadOptions::default().filename_policy(FilenamePolicy::Sanitize);
+    let artifacts = update
+        .download_with(&out_dir, &options)
+        .await
+        .expect("Failed to download update");
+    assert_eq!(artifacts[0].file(), &out_dir.join(".._escape/test.txt"));
 }
 
 #[tokio::test]
-async fn download_stream() {
+async fn download_layout() {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use assert_matches::assert_matches;
+    use hawkbit::ddi::{Artifact, Chunk, DownloadLayout, DownloadOptions};
+
     init();
 
+    let artifact = || {
+        vec![(
+            artifact_path(),
+            "5eb63bbbe01eeed093cb22bb8f5acdc3",
+            "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed",
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9",
+        )]
+    };
+
     let server = ServerBuilder::default().build();
     let (client, target) = add_target(&server, "Target1");
+    target.push_deployment(
+        DeploymentBuilder::new("10", Type::Forced, Type::Attempt)
+            .chunk(ChunkProtocol::BOTH, "os", "1.0", "image", artifact())
+            .chunk(ChunkProtocol::BOTH, "bApp", "2.0", "image", artifact())
+            .build(),
+    );
 
-    target.push_deployment(get_deployment(false, true));
     let reply = client.poll().await.expect("poll failed");
-
     let update = reply.update().expect("missing update");
     let update = update.fetch().await.expect("failed to fetch update info");
-    let chunk = update.chunks().next().unwrap();
-    let art = chunk.artifacts().next().unwrap();
-
-    async fn check_download(mut stream: Box<dyn Stream<Item = Result<Bytes, Error>> + Unpin>) {
-        let mut downloaded: Vec<u8> = Vec::new();
-        while let Some(b) = stream.next().await {
-            downloaded.extend(b.unwrap().as_ref());
-        }
-
-        // Compare downloaded content with the actual file
-        let mut art_file = File::open(&artifact_path()).expect("failed to open artifact");
-        let mut expected = Vec::new();
-        art_file
-            .read_to_end(&mut expected)
-            .expect("failed to read artifact");
-
-        assert_eq!(downloaded, expected);
-    }
 
-    // Download artifact using the stream API
-    let stream = art
-        .download_stream()
+    let out_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    let options = DownloadOptions::default().layout(DownloadLayout::ChunkVersion);
+    let artifacts = update
+        .download_with(out_dir.path(), &options)
         .await
-        .expect("failed to get download stream");
-    check_download(Box::new(stream)).await;
+        .expect("Failed to download update");
 
-    cfg_if::cfg_if! {
-        if #[cfg(feature = "hash-md5")] {
-            let stream = art
-                .download_stream_with_md5_check()
-                .await
-                .expect("failed to get download stream");
-            check_download(Box::new(stream)).await;
-        }
-    }
+    assert_eq!(artifacts.len(), 2);
+    assert_eq!(
+        artifacts[0].file(),
+        &out_dir.path().join("os/image-1.0/test.txt")
+    );
+    assert_eq!(artifacts[0].part(), "os");
+    assert_eq!(artifacts[0].name(), "image");
+    assert_eq!(artifacts[0].version(), "1.0");
+    assert_eq!(
+        artifacts[1].file(),
+        &out_dir.path().join("bApp/image-2.0/test.txt")
+    );
+    assert_eq!(artifacts[1].part(), "bApp");
+    assert_eq!(artifacts[1].version(), "2.0");
 
-    cfg_if::cfg_if! {
-        if #[cfg(feature = "hash-sha1")] {
-            let stream = art
-                .download_stream_with_sha1_check()
-                .await
-                .expect("failed to get download stream");
-            check_download(Box::new(stream)).await;
-        }
-    }
+    let options = DownloadOptions::default().layout(DownloadLayout::Custom(Arc::new(
+        |chunk: &Chunk<'_>, artifact: &Artifact<'_>| {
+            PathBuf::from(format!("{}_{}", chunk.version(), artifact.filename()))
+        },
+    )));
+    let artifacts = update
+        .download_with(out_dir.path(), &options)
+        .await
+        .expect("Failed to download update");
+    assert_eq!(artifacts[1].file(), &out_dir.path().join("2.0_test.txt"));
 
-    cfg_if::cfg_if! {
-        if #[cfg(feature = "hash-sha256")] {
-            let stream = art
-                .download_stream_with_sha256_check()
-                .await
-                .expect("failed to get download stream");
-            check_download(Box::new(stream)).await;
-        }
-    }
+    let options = DownloadOptions::default().layout(DownloadLayout::Custom(Arc::new(
+        |_: &Chunk<'_>, artifact: &Artifact<'_>| PathBuf::from("..").join(artifact.filename()),
+    )));
+    assert_matches!(
+        update.download_with(out_dir.path(), &options).await,
+        Err(Error::UnsafeFilename(_))
+    );
 }
 
-#[cfg(feature = "hash-digest")]
 #[tokio::test]
-async fn wrong_checksums() {
+async fn concurrent_download() {
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+
     use assert_matches::assert_matches;
-    use hawkbit::ddi::ChecksumType;
+    use hawkbit::ddi::{DownloadOptions, DownloadProgress};
 
     init();
 
+    let src_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    let source = |name: &str, content: &str| {
+        let path = src_dir.path().join(name);
+        std::fs::write(&path, content).expect("failed to write artifact");
+        let (md5, sha1, sha256) = match content {
+            "aaaa" => (
+                "74b87337454200d4d33f80c4663dc5e5",
+                "70c881d4a26984ddce795f6f71817c9cf4480e79",
+                "61be55a8e2f6b4e172338bddf184d6dbee29c98853e0a0485ecee7f27b9af0b4",
+            ),
+            "bbbbbb" => (
+                "875f26fdb1cecf20ceb4ca028263dec6",
+                "0e03c6205ea671d7d41a0e3aabfc9d15d97e5ed3",
+                "4625fd63b0e96fc0d656ae7381605e48d4a0f63a319fc743adf22688613883c7",
+            ),
+            "cc" => (
+                "e0323a9039add2978bf5b49550572c7c",
+                "bdb480de655aa6ec75ca058c849c4faf3c0f75b1",
+                "355b1bbfc96725cdce8f4a2708fda310a80e6d13315aec4e5eed2a75fe8032ce",
+            ),
+            "dddddddd" => (
+                "ef800207a3648c7c1ef3e9fe544f17f0",
+                "d36da3e6884f6d1e9e7983ff13e99cf5c8f5745a",
+                "b32914cd620087fa50645bbba8268fc3bc9d92aeb427bc6b985477b9b4a65830",
+            ),
+            // never completely downloaded
+            _ => ("md5", "sha1", "sha256"),
+        };
+        (path, md5, sha1, sha256)
+    };
+
     let server = ServerBuilder::default().build();
     let (client, target) = add_target(&server, "Target1");
+    target.push_deployment(
+        DeploymentBuilder::new("10", Type::Forced, Type::Attempt)
+            .chunk(
+                ChunkProtocol::BOTH,
+                "app1",
+                "1.0",
+                "chunk1",
+                vec![source("a.bin", "aaaa"), source("b.bin", "bbbbbb")],
+            )
+            .chunk(
+                ChunkProtocol::BOTH,
+                "app2",
+                "1.0",
+                "chunk2",
+                vec![source("c.bin", "cc"), source("d.bin", "dddddddd")],
+            )
+            .build(),
+    );
 
-    target.push_deployment(get_deployment(false, false));
     let reply = client.poll().await.expect("poll failed");
-
     let update = reply.update().expect("missing update");
     let update = update.fetch().await.expect("failed to fetch update info");
-    let chunk = update.chunks().next().unwrap();
-    let art = chunk.artifacts().next().unwrap();
+
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let observed = progress.clone();
+    let options =
+        DownloadOptions::default()
+            .concurrency(3)
+            .progress(move |p: &DownloadProgress| {
+                observed.lock().unwrap().push(p.overall_downloaded());
+            });
 
     let out_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
-    let downloaded = art
-        .download(out_dir.path())
+    let artifacts = update
+        .download_with(out_dir.path(), &options)
         .await
-        .expect("failed to download artifact");
+        .expect("Failed to download update");
 
-    #[cfg(feature = "hash-md5")]
-    assert_matches!(
-        downloaded.check_md5().await,
-        Err(Error::ChecksumError(ChecksumType::Md5))
+    // the artifacts are returned in the order of the deployment
+    let files: Vec<_> = artifacts
+        .iter()
+        .map(|a| std::fs::read_to_string(a.file()).unwrap())
+        .collect();
+    assert_eq!(files, vec!["aaaa", "bbbbbb", "cc", "dddddddd"]);
+    assert_eq!(artifacts[2].file(), &out_dir.path().join("chunk2/c.bin"));
+    assert_eq!(progress.lock().unwrap().iter().max(), Some(&20));
+
+    // a failed download interrupts the other ones, keeping their partial file
+    let server = ServerBuilder::default().build();
+    let (client, target) = add_target(&server, "Target1");
+    target.push_deployment(
+        DeploymentBuilder::new("11", Type::Forced, Type::Attempt)
+            .chunk_with_mock(
+                ChunkProtocol::BOTH,
+                "app1",
+                "1.0",
+                "slow",
+                vec![source("slow.bin", "slow data")],
+                Box::new(|when: When, then: Then| {
+                    when.method(GET).path("/download/slow.bin");
+                    then.status(206).delay(Duration::from_secs(5)).body(" data");
+                }),
+            )
+            .chunk_with_mock(
+                ChunkProtocol::BOTH,
+                "app2",
+                "1.0",
+                "missing",
+                vec![source("missing.bin", "missing")],
+                Box::new(|when: When, then: Then| {
+                    when.method(GET).path("/download/missing.bin");
+                    then.status(404);
+                }),
+            )
+            .build(),
     );
-    #[cfg(feature = "hash-sha1")]
+
+    let reply = client.poll().await.expect("poll failed");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+
+    let part = out_dir.path().join("slow/slow.bin.part");
+    std::fs::create_dir_all(part.parent().unwrap()).unwrap();
+    std::fs::write(&part, "slow").unwrap();
+
+    let start = Instant::now();
     assert_matches!(
-        downloaded.check_sha1().await,
-        Err(Error::ChecksumError(ChecksumType::Sha1))
+        update
+            .download_with(out_dir.path(), &DownloadOptions::default().concurrency(2))
+            .await,
+        Err(Error::NotFound { .. })
     );
-    #[cfg(feature = "hash-sha256")]
-    assert_matches!(
-        downloaded.check_sha256().await,
-        Err(Error::ChecksumError(ChecksumType::Sha256))
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(std::fs::read_to_string(&part).unwrap(), "slow");
+}
+
+#[tokio::test]
+async fn download_progress() {
+    use std::sync::{Arc, Mutex};
+
+    use hawkbit::ddi::{DownloadOptions, DownloadProgress, ProgressFeedback};
+
+    init();
+
+    let server = ServerBuilder::default().build();
+    let (client, target) = add_target(&server, "Target1");
+    target.push_deployment(get_deployment(false, true));
+
+    let reply = client.poll().await.expect("poll failed");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+
+    let started = target.expect_deployment_feedback(
+        "10",
+        Execution::Download,
+        Finished::None,
+        Some(json!({"cnt": 0, "of": 100})),
+        vec!["Downloading test.txt"],
+    );
+    let completed = target.expect_deployment_feedback(
+        "10",
+        Execution::Download,
+        Finished::None,
+        Some(json!({"cnt": 100, "of": 100})),
+        vec!["Downloading test.txt"],
     );
 
-    cfg_if::cfg_if! {
-        if #[cfg(feature = "hash-md5")] {
-            let stream = art
-                .download_stream_with_md5_check()
-                .await
-                .expect("failed to get download stream");
-            let end = stream.skip_while(|b| future::ready(b.is_ok())).next().await;
-            assert_matches!(end, Some(Err(Error::ChecksumError(ChecksumType::Md5))));
-        }
-    }
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let observed = events.clone();
+    let options = DownloadOptions::default()
+        .progress(move |progress: &DownloadProgress| {
+            observed.lock().unwrap().push(progress.clone());
+        })
+        .progress_feedback(ProgressFeedback::default());
 
-    cfg_if::cfg_if! {
-        if #[cfg(feature = "hash-sha1")] {
-            let stream = art
-                .download_stream_with_sha1_check()
-                .await
-                .expect("failed to get download stream");
-            let end = stream.skip_while(|b| future::ready(b.is_ok())).next().await;
-            assert_matches!(end, Some(Err(Error::ChecksumError(ChecksumType::Sha1))));
-        }
+    let out_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    let artifacts = update
+        .download_with(out_dir.path(), &options)
+        .await
+        .expect("Failed to download update");
+    assert_eq!(artifacts.len(), 3);
+
+    let events = events.lock().unwrap();
+    let last = events.last().expect("no progress reported");
+    assert_eq!(last.overall_size(), 33);
+    assert_eq!(last.overall_downloaded(), 33);
+    for part in ["app-both", "app-http", "app-https"] {
+        let progress = events
+            .iter()
+            .rev()
+            .find(|p| p.part() == part)
+            .expect("no progress reported for chunk");
+        assert_eq!(progress.filename(), "test.txt");
+        assert_eq!(progress.name(), "some-chunk");
+        assert_eq!(progress.version(), "1.0");
+        assert_eq!(progress.size(), 11);
+        assert_eq!(progress.downloaded(), 11);
     }
 
-    cfg_if::cfg_if! {
-        if #[cfg(feature = "hash-sha256")] {
-            let stream = art
-                .download_stream_with_sha256_check()
-                .await
-                .expect("failed to get download stream");
-            let end = stream.skip_while(|b| future::ready(b.is_ok())).next().await;
-            assert_matches!(end, Some(Err(Error::ChecksumError(ChecksumType::Sha256))));
-        }
-    }
+    // intermediate progress is throttled
+    assert_eq!(started.calls(), 1);
+    assert_eq!(completed.calls(), 1);
 }
 
 #[tokio::test]
-async fn cancel_action() {
+async fn send_deployment_feedback() {
     init();
 
     let server = ServerBuilder::default().build();
+    let deploy = get_deployment(false, true);
+    let deploy_id = deploy.id.clone();
     let (client, target) = add_target(&server, "Target1");
-    target.cancel_action("10");
+    target.push_deployment(deploy);
 
     let reply = client.poll().await.expect("poll failed");
-    assert!(reply.config_data_request().is_none());
-    assert!(reply.update().is_none());
-    let cancel_action = reply.cancel_action().expect("missing cancel action");
-
-    let id = cancel_action
-        .id()
-        .await
-        .expect("failed to fetch cancel action id");
-    assert_eq!(id, "10");
-
-    assert_eq!(target.poll_hits(), 1);
-    assert_eq!(target.cancel_action_hits(), 1);
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
 
-    let mut mock = target.expect_cancel_feedback(
-        &id,
+    // Send feedback without progress
+    let mut mock = target.expect_deployment_feedback(
+        &deploy_id,
         Execution::Proceeding,
         Finished::None,
-        vec!["Cancelling"],
+        None,
+        vec!["Downloading"],
     );
     assert_eq!(mock.calls(), 0);
 
-    cancel_action
-        .send_feedback(Execution::Proceeding, Finished::None, vec!["Cancelling"])
+    update
+        .send_feedback(Execution::Proceeding, Finished::None, vec!["Downloading"])
+        .await
+        .expect("Failed to send feedback");
+    assert_eq!(mock.calls(), 1);
+    mock.delete();
+
+    // Send feedback with progress
+    let mut mock = target.expect_deployment_feedback(
+        &deploy_id,
+        Execution::Closed,
+        Finished::Success,
+        Some(json!({"awesome": true})),
+        vec!["Done"],
+    );
+    assert_eq!(mock.calls(), 0);
+
+    #[derive(Serialize)]
+    struct Progress {
+        awesome: bool,
+    }
+    let progress = Progress { awesome: true };
+
+    update
+        .send_feedback_with_progress(
+            Execution::Closed,
+            Finished::Success,
+            Some(progress),
+            vec!["Done"],
+        )
         .await
         .expect("Failed to send feedback");
     assert_eq!(mock.calls(), 1);
@@ -690,131 +962,2048 @@ async fn cancel_action() {
 }
 
 #[tokio::test]
-async fn client_authorization() {
+async fn action_state() {
+    use hawkbit::ddi::{ActionPhase, FileStateStore, StateStore};
+
     init();
 
-    let server = ServerBuilder::default()
-        .target_authorization(hawkbit_mock::ddi::TargetAuthorization::None)
-        .build();
-    let (client, _target) = add_target(&server, "Target1");
+    let server = ServerBuilder::default().build();
+    let deploy = get_deployment(false, true);
+    let deploy_id = deploy.id.clone();
+    let (client, target) = add_target(&server, "Target1");
+    target.push_deployment(deploy);
 
-    client.poll().await.expect("poll failed");
+    let dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    let store = FileStateStore::new(&dir.path().join("state.json"));
+    assert!(store.load().await.expect("failed to load state").is_none());
 
-    let server = ServerBuilder::default()
-        .target_authorization(hawkbit_mock::ddi::TargetAuthorization::TargetToken)
-        .build();
-    let _ = add_target(&server, "Target2");
-    // create the client manually to control the authorization method
-    let client1 = Client::new(
-        &server.base_url(),
-        &server.tenant,
-        "Target2",
-        hawkbit::ddi::ClientAuthorization::TargetToken("KeyTarget2".to_string()),
-        None,
-        None,
-        None,
-    )
-    .unwrap();
-    let client2 = Client::new(
-        &server.base_url(),
-        &server.tenant,
-        "Target2",
-        hawkbit::ddi::ClientAuthorization::TargetToken("WrongToken".to_string()),
-        None,
-        None,
-        None,
-    )
-    .unwrap();
-    let client3 = Client::new(
-        &server.base_url(),
-        &server.tenant,
-        "Target2",
-        hawkbit::ddi::ClientAuthorization::None,
-        None,
-        None,
-        None,
-    )
-    .unwrap();
+    {
+        let reply = client.poll().await.expect("poll failed");
+        let update = reply.update().expect("missing update");
+        let update = update.fetch().await.expect("failed to fetch update info");
+        store
+            .save(&update.state(ActionPhase::Installed))
+            .await
+            .expect("failed to save state");
+    }
 
-    client1.poll().await.expect("poll failed");
-    client2
-        .poll()
-        .await
-        .expect_err("poll with wrong token succeeded");
-    client3
-        .poll()
+    // restore the update as if the device had been rebooted
+    let state = store
+        .load()
         .await
-        .expect_err("poll without token succeeded");
+        .expect("failed to load state")
+        .expect("missing state");
+    assert_eq!(state.action_id(), deploy_id);
+    assert_eq!(state.phase(), ActionPhase::Installed);
 
-    let server = ServerBuilder::default()
-        .target_authorization(hawkbit_mock::ddi::TargetAuthorization::GatewayToken)
-        .build();
-    let _ = add_target(&server, "Target3");
-    // create the client manually to control the authorization method
-    let client1 = Client::new(
-        &server.base_url(),
-        &server.tenant,
-        "Target3",
-        hawkbit::ddi::ClientAuthorization::GatewayToken("KeyDEFAULT".to_string()),
-        None,
-        None,
-        None,
-    )
-    .unwrap();
-    let client2 = Client::new(
+    let client = Client::new(
         &server.base_url(),
         &server.tenant,
-        "Target3",
-        hawkbit::ddi::ClientAuthorization::GatewayToken("WrongToken".to_string()),
+        &target.name,
+        target.client_auth.clone(),
         None,
         None,
         None,
     )
-    .unwrap();
-    let client3 = Client::new(
-        &server.base_url(),
-        &server.tenant,
-        "Target3",
-        hawkbit::ddi::ClientAuthorization::TargetToken("KeyTarget3".to_string()),
-        None,
-        None,
+    .expect("DDI creation failed");
+    let update = state.into_update(&client);
+    assert_eq!(update.action_id(), deploy_id);
+    assert_eq!(update.download_type(), Type::Forced);
+    assert_eq!(
+        update.maintenance_window(),
+        Some(MaintenanceWindow::Available)
+    );
+    let chunks: Vec<_> = update.chunks().map(|c| c.part().to_string()).collect();
+    assert_eq!(chunks, vec!["app-both", "app-http", "app-https"]);
+    for chunk in update.chunks() {
+        let out_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+        chunk
+            .download(out_dir.path())
+            .await
+            .expect("Failed to download restored update");
+    }
+
+    let mock = target.expect_deployment_feedback(
+        &deploy_id,
+        Execution::Closed,
+        Finished::Success,
+        None,
+        vec![],
+    );
+    update
+        .send_feedback(Execution::Closed, Finished::Success, vec![])
+        .await
+        .expect("Failed to send feedback");
+    assert_eq!(mock.calls(), 1);
+
+    store.clear().await.expect("failed to clear state");
+    assert!(store.load().await.expect("failed to load state").is_none());
+}
+
+#[tokio::test]
+async fn confirmation() {
+    init();
+
+    let server = ServerBuilder::default().build();
+    let deploy = get_deployment(true, true);
+    let deploy_id = deploy.id.clone();
+    let (client, target) = add_target(&server, "Target1");
+    target.push_deployment(deploy);
+
+    let reply = client.poll().await.expect("poll failed");
+    let confirmation = reply
+        .confirmation_base()
+        .expect("missing confirmation request");
+
+    // Decline the confirmation
+    let mut mock = target.expect_confirmation_feedback(
+        &deploy_id,
+        Some(-1),
+        ConfirmationResponse::Denied,
+        vec![],
+    );
+    assert_eq!(mock.calls(), 0);
+
+    confirmation
+        .decline()
+        .await
+        .expect("Failed to send feedback");
+    assert_eq!(mock.calls(), 1);
+    mock.delete();
+
+    let reply = client.poll().await.expect("poll failed");
+    let confirmation = reply
+        .confirmation_base()
+        .expect("missing confirmation request");
+    let update_info = confirmation
+        .update_info()
+        .await
+        .expect("failed to fetch update info");
+
+    let action_id = update_info.action_id();
+    assert_eq!(action_id, deploy_id);
+
+    assert_eq!(
+        update_info.metadata(),
+        confirmation.metadata().await.unwrap()
+    );
+
+    // Accept the confirmation
+    let mut mock = target.expect_confirmation_feedback(
+        &deploy_id,
+        Some(1),
+        ConfirmationResponse::Confirmed,
+        vec![],
+    );
+    assert_eq!(mock.calls(), 0);
+
+    confirmation
+        .confirm()
+        .await
+        .expect("Failed to send feedback");
+    assert_eq!(mock.calls(), 1);
+    mock.delete();
+}
+
+#[tokio::test]
+async fn confirmation_metadata() {
+    init();
+
+    let server = ServerBuilder::default().build();
+    let deploy = get_deployment(true, true);
+    let (client, target) = add_target(&server, "Target1");
+    target.push_deployment(deploy);
+
+    let reply = client.poll().await.expect("poll failed");
+    let confirmation = reply
+        .confirmation_base()
+        .expect("missing confirmation request");
+
+    let metadata = confirmation
+        .metadata()
+        .await
+        .expect("failed to fetch metadata");
+    assert_eq!(metadata.len(), 3);
+    assert_eq!(metadata[0], ("key1".to_string(), "value1".to_string()));
+    assert_eq!(metadata[1], ("key2".to_string(), "value2".to_string()));
+    assert_eq!(metadata[2], ("key3".to_string(), "value3".to_string()));
+}
+
+#[tokio::test]
+async fn config_then_deploy() {
+    init();
+
+    let server = ServerBuilder::default().build();
+    let (client, target) = add_target(&server, "Target1");
+
+    let reply = client.poll().await.expect("poll failed");
+    assert!(reply.config_data_request().is_none());
+    assert!(reply.update().is_none());
+
+    // server requests config
+    let expected_config_data = json!({
+        "mode" : "merge",
+        "data" : {
+            "awesome" : true,
+        },
+        "status" : {
+            "result" : {
+            "finished" : "success"
+            },
+            "execution" : "closed",
+            "details" : [ "Some stuffs" ]
+        }
+    });
+    target.request_config(expected_config_data);
+
+    let reply = client.poll().await.expect("poll failed");
+    assert!(reply.config_data_request().is_some());
+    assert!(reply.update().is_none());
+
+    // server pushes an update
+    target.push_deployment(get_deployment(false, true));
+
+    let reply = client.poll().await.expect("poll failed");
+    assert!(reply.config_data_request().is_some());
+    assert!(reply.update().is_some());
+}
+
+/// Serve the test artifact, closing the first connection in the middle of the transfer.
+///
+/// Range requests are honored unless `ignore_range` is set.
+fn flaky_artifact_server(ignore_range: bool) -> String {
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind");
+    let url = format!("http://{}/test.txt", listener.local_addr().unwrap());
+
+    std::thread::spawn(move || {
+        for (i, stream) in listener.incoming().enumerate() {
+            let mut stream = stream.unwrap();
+            let mut range = None;
+            for line in BufReader::new(stream.try_clone().unwrap()).lines() {
+                let line = line.unwrap();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(value) = line.to_lowercase().strip_prefix("range: bytes=") {
+                    range = value.trim_end_matches('-').parse::<usize>().ok();
+                }
+            }
+
+            let data = "hello world";
+            let reply = match range {
+                _ if i == 0 => format!("HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\n{}", &data[..5]),
+                Some(offset) if !ignore_range => format!(
+                    "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-10/11\r\nContent-Length: {}\r\n\r\n{}",
+                    offset,
+                    11 - offset,
+                    &data[offset..]
+                ),
+                _ => format!("HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\n{}", data),
+            };
+            stream.write_all(reply.as_bytes()).unwrap();
+        }
+    });
+
+    url
+}
+
+/// Server providing a deployment with a single artifact having `links` as download links.
+fn artifact_links_server(
+    links: impl Fn(&httpmock::MockServer) -> serde_json::Value,
+) -> httpmock::MockServer {
+    let server = httpmock::MockServer::start();
+    let deployment_url = server.url("/DEFAULT/controller/v1/Target1/deploymentBase/10");
+    server.mock(|when, then| {
+        when.method(GET).path("/DEFAULT/controller/v1/Target1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({
+                "config": {"polling": {"sleep": "00:01:00"}},
+                "_links": {"deploymentBase": {"href": deployment_url}}
+            }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/DEFAULT/controller/v1/Target1/deploymentBase/10");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({
+                "id": "10",
+                "deployment": {
+                    "download": "forced",
+                    "update": "forced",
+                    "chunks": [{
+                        "part": "app",
+                        "version": "1.0",
+                        "name": "some-chunk",
+                        "artifacts": [{
+                            "filename": "test.txt",
+                            "hashes": {
+                                "md5": "5eb63bbbe01eeed093cb22bb8f5acdc3",
+                                "sha1": "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed",
+                                "sha256": "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+                            },
+                            "size": 11,
+                            "_links": links(&server)
+                        }]
+                    }]
+                }
+            }));
+    });
+    server
+}
+
+#[tokio::test]
+async fn resumable_download_stream() {
+    use hawkbit::ddi::RetryPolicy;
+
+    init();
+
+    for ignore_range in [false, true] {
+        let artifact_url = flaky_artifact_server(ignore_range);
+
+        let server = artifact_links_server(|_| json!({"download-http": {"href": artifact_url}}));
+
+        let client = Client::builder(&server.base_url(), "DEFAULT", "Target1")
+            .retry_policy(RetryPolicy::default().initial_backoff(Duration::from_millis(10)))
+            .build()
+            .expect("DDI creation failed");
+        let reply = client.poll().await.expect("poll failed");
+        let update = reply.update().expect("missing update");
+        let update = update.fetch().await.expect("failed to fetch update info");
+        let chunk = update.chunks().next().unwrap();
+        let art = chunk.artifacts().next().unwrap();
+
+        // the stream reconnects after the first connection has been closed
+        let stream = art
+            .download_stream()
+            .await
+            .expect("failed to get download stream");
+        let data: Vec<Bytes> = stream.try_collect().await.expect("download failed");
+        assert_eq!(data.concat(), b"hello world");
+    }
+}
+
+#[tokio::test]
+async fn download_retry_attempts() {
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use assert_matches::assert_matches;
+    use hawkbit::ddi::RetryPolicy;
+
+    init();
+
+    // the connection is lost during the first transfer, then the server is unavailable
+    let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind");
+    let artifact_url = format!("http://{}/test.txt", listener.local_addr().unwrap());
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            for line in BufReader::new(stream.try_clone().unwrap()).lines() {
+                if line.unwrap().is_empty() {
+                    break;
+                }
+            }
+
+            let reply = match counter.fetch_add(1, Ordering::SeqCst) % 3 {
+                0 => "HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello",
+                _ => "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n",
+            };
+            stream.write_all(reply.as_bytes()).unwrap();
+        }
+    });
+
+    let server = artifact_links_server(|_| json!({"download-http": {"href": artifact_url}}));
+    let client = Client::builder(&server.base_url(), "DEFAULT", "Target1")
+        .retry_policy(
+            RetryPolicy::default()
+                .max_attempts(3)
+                .initial_backoff(Duration::from_millis(10)),
+        )
+        .build()
+        .expect("DDI creation failed");
+    let reply = client.poll().await.expect("poll failed");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+    let chunk = update.chunks().next().unwrap();
+    let art = chunk.artifacts().next().unwrap();
+
+    // the requests sent to resume the transfer are not retried on their own
+    let out_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    assert_matches!(
+        art.download(out_dir.path()).await,
+        Err(Error::ServerUnavailable { .. })
+    );
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+    let mut data = Vec::new();
+    assert_matches!(
+        art.download_to(&mut data).await,
+        Err(Error::ServerUnavailable { .. })
+    );
+    assert_eq!(requests.load(Ordering::SeqCst), 6);
+}
+
+#[tokio::test]
+async fn transport_preference() {
+    use assert_matches::assert_matches;
+    use hawkbit::ddi::TransportPreference;
+
+    init();
+
+    let server = artifact_links_server(|server| {
+        json!({
+            "download": {"href": server.url("/https/test.txt")},
+            "download-http": {"href": server.url("/http/test.txt")}
+        })
+    });
+    let https = server.mock(|when, then| {
+        when.method(GET).path("/https/test.txt");
+        then.status(404);
+    });
+    let http = server.mock(|when, then| {
+        when.method(GET).path("/http/test.txt");
+        then.status(200).body("hello world");
+    });
+
+    let download = |preference: TransportPreference| {
+        let client = Client::builder(&server.base_url(), "DEFAULT", "Target1")
+            .transport_preference(preference)
+            .build()
+            .expect("DDI creation failed");
+        async move {
+            let reply = client.poll().await.expect("poll failed");
+            let update = reply.update().expect("missing update");
+            let update = update.fetch().await.expect("failed to fetch update info");
+            let chunk = update.chunks().next().unwrap();
+            let art = chunk.artifacts().next().unwrap();
+            let mut data = Vec::new();
+            art.download_to(&mut data).await.map(|_| data)
+        }
+    };
+
+    // fallback to http when the https download fails
+    let data = download(TransportPreference::PreferHttps)
+        .await
+        .expect("download failed");
+    assert_eq!(data, b"hello world");
+    assert_eq!(https.calls(), 1);
+    assert_eq!(http.calls(), 1);
+
+    let data = download(TransportPreference::PreferHttp)
+        .await
+        .expect("download failed");
+    assert_eq!(data, b"hello world");
+    assert_eq!(https.calls(), 1);
+    assert_eq!(http.calls(), 2);
+
+    assert_matches!(
+        download(TransportPreference::HttpsOnly).await,
+        Err(Error::NotFound { .. })
+    );
+    assert_eq!(https.calls(), 2);
+    assert_eq!(http.calls(), 2);
+
+    // no link can be used
+    let server = artifact_links_server(
+        |server| json!({"download-http": {"href": server.url("/http/test.txt")}}),
+    );
+    let client = Client::builder(&server.base_url(), "DEFAULT", "Target1")
+        .transport_preference(TransportPreference::HttpsOnly)
+        .build()
+        .expect("DDI creation failed");
+    let reply = client.poll().await.expect("poll failed");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+    let chunk = update.chunks().next().unwrap();
+    let art = chunk.artifacts().next().unwrap();
+    assert_matches!(
+        art.download_to(&mut Vec::new()).await,
+        Err(Error::MissingDownloadLink)
+    );
+}
+
+#[tokio::test]
+async fn md5sum_file() {
+    use assert_matches::assert_matches;
+
+    init();
+
+    let server = ServerBuilder::default().build();
+    let (client, target) = add_target(&server, "Target1");
+    target.push_deployment(get_deployment(false, true));
+
+    let reply = client.poll().await.expect("poll failed");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+    let chunk = update.chunks().next().unwrap();
+    let art = chunk.artifacts().next().unwrap();
+
+    let md5sum = art
+        .fetch_md5sum_file()
+        .await
+        .expect("failed to fetch MD5SUM file");
+    assert_eq!(md5sum.md5(), "5eb63bbbe01eeed093cb22bb8f5acdc3");
+    assert_eq!(md5sum.filename(), Some("test.txt"));
+    art.check_md5sum_file()
+        .await
+        .expect("MD5SUM file does not match");
+
+    // the MD5SUM file is checked before downloading
+    let server = artifact_links_server(|server| {
+        json!({
+            "download-http": {"href": server.url("/download/test.txt")},
+            "md5sum-http": {"href": server.url("/download/test.txt.MD5SUM")}
+        })
+    });
+    let download = server.mock(|when, then| {
+        when.method(GET).path("/download/test.txt");
+        then.status(200).body("hello world");
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/download/test.txt.MD5SUM");
+        then.status(200)
+            .body("0123456789abcdef0123456789abcdef  test.txt");
+    });
+
+    let client = Client::builder(&server.base_url(), "DEFAULT", "Target1")
+        .check_md5sum_file(true)
+        .build()
+        .expect("DDI creation failed");
+    let reply = client.poll().await.expect("poll failed");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+    let chunk = update.chunks().next().unwrap();
+    let art = chunk.artifacts().next().unwrap();
+
+    assert_matches!(
+        art.download_to(&mut Vec::new()).await,
+        Err(Error::Md5SumMismatch { md5sum, .. }) if md5sum == "0123456789abcdef0123456789abcdef"
+    );
+    let out_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    assert_matches!(
+        art.download(out_dir.path()).await,
+        Err(Error::Md5SumMismatch { .. })
+    );
+    assert_eq!(download.calls(), 0);
+}
+
+#[tokio::test]
+async fn redirect() {
+    use assert_matches::assert_matches;
+    use hawkbit::ddi::RedirectPolicy;
+    use reqwest::header::{HeaderName, HeaderValue};
+    use reqwest::StatusCode;
+
+    init();
+
+    let hashes = (
+        "5eb63bbbe01eeed093cb22bb8f5acdc3",
+        "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed",
+        "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9",
+    );
+    let src_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    let file = |name: &str| {
+        let path = src_dir.path().join(name);
+        std::fs::write(&path, "hello world").expect("failed to write artifact");
+        path
+    };
+
+    // the artifacts are stored on another origin
+    let storage = httpmock::MockServer::start();
+    let external = storage.mock(|when, then| {
+        when.method(GET)
+            .path("/bucket/test.txt")
+            .header_missing("Authorization")
+            .header_missing("X-Secret");
+        then.status(200).body("hello world");
+    });
+    storage.mock(|when, then| {
+        when.method(GET).path("/bucket/loop");
+        then.status(302).header("Location", "/bucket/loop");
+    });
+    storage.mock(|when, then| {
+        when.method(GET).path("/bucket/nowhere");
+        then.status(302).body("moved");
+    });
+
+    let server = ServerBuilder::default().build();
+    let target = server.add_target("Target1");
+    let client = |policy: RedirectPolicy| {
+        Client::builder(&server.base_url(), &server.tenant, &target.name)
+            .authorization(target.client_auth.clone())
+            .default_header(
+                HeaderName::from_static("x-secret"),
+                HeaderValue::from_static("secret"),
+            )
+            .redirect_policy(policy)
+            .build()
+            .expect("DDI creation failed")
+    };
+
+    let redirect = |from: &'static str, to: String| -> Box<dyn Fn(When, Then)> {
+        Box::new(move |when: When, then: Then| {
+            when.method(GET).path(from);
+            then.status(307).header("Location", to.as_str());
+        })
+    };
+    let artifact = |path: PathBuf| vec![(path, hashes.0, hashes.1, hashes.2)];
+    target.push_deployment(
+        DeploymentBuilder::new("10", Type::Forced, Type::Attempt)
+            .chunk_with_mock(
+                ChunkProtocol::HTTP,
+                "app",
+                "1.0",
+                "external",
+                artifact(artifact_path()),
+                redirect("/download/test.txt", storage.url("/bucket/test.txt")),
+            )
+            .chunk_with_mock(
+                ChunkProtocol::HTTP,
+                "app",
+                "1.0",
+                "loop",
+                artifact(file("loop")),
+                redirect("/download/loop", storage.url("/bucket/loop")),
+            )
+            .chunk_with_mock(
+                ChunkProtocol::HTTP,
+                "app",
+                "1.0",
+                "nowhere",
+                artifact(file("nowhere")),
+                redirect("/download/nowhere", storage.url("/bucket/nowhere")),
+            )
+            .chunk_with_mock(
+                ChunkProtocol::HTTP,
+                "app",
+                "1.0",
+                "same-origin",
+                artifact(file("same-origin")),
+                redirect("/download/same-origin", "/download/moved.txt".to_string()),
+            )
+            // requires the authorization header of the target
+            .chunk(
+                ChunkProtocol::HTTP,
+                "app",
+                "1.0",
+                "moved",
+                artifact(file("moved.txt")),
+            )
+            .build(),
+    );
+
+    let download = |client: Client, chunk: &'static str| async move {
+        let reply = client.poll().await.expect("poll failed");
+        let update = reply.update().expect("missing update");
+        let update = update.fetch().await.expect("failed to fetch update info");
+        let chunk = update.chunks().find(|c| c.name() == chunk).unwrap();
+        let art = chunk.artifacts().next().unwrap();
+        let mut data = Vec::new();
+        art.download_to(&mut data).await.map(|_| data)
+    };
+
+    // credentials are not sent to the other origin
+    let data = download(client(RedirectPolicy::default()), "external")
+        .await
+        .expect("download failed");
+    assert_eq!(data, b"hello world");
+    assert_eq!(external.calls(), 1);
+
+    // but they are on the same origin
+    let data = download(client(RedirectPolicy::default()), "same-origin")
+        .await
+        .expect("download failed");
+    assert_eq!(data, b"hello world");
+
+    assert_matches!(
+        download(client(RedirectPolicy::default()), "loop").await,
+        Err(Error::TooManyRedirects)
+    );
+    // a redirect without location cannot be followed
+    assert_matches!(
+        download(client(RedirectPolicy::default()), "nowhere").await,
+        Err(Error::HttpStatus { status, body: Some(body), .. })
+            if status == StatusCode::FOUND && body == "moved"
+    );
+    assert_matches!(
+        download(client(RedirectPolicy::none()), "external").await,
+        Err(Error::TooManyRedirects)
+    );
+
+    let policy = RedirectPolicy::default().allowed_hosts(&["storage.example.com"]);
+    assert_matches!(
+        download(client(policy), "external").await,
+        Err(Error::RedirectRefused(url)) if url == storage.url("/bucket/test.txt")
+    );
+    let policy = RedirectPolicy::default().allowed_hosts(&["127.0.0.1", "localhost"]);
+    download(client(policy), "external")
+        .await
+        .expect("download failed");
+    assert_eq!(external.calls(), 2);
+}
+
+#[tokio::test]
+async fn download_cancel() {
+    use assert_matches::assert_matches;
+    use hawkbit::ddi::DownloadOptions;
+    use tokio_util::sync::CancellationToken;
+
+    init();
+
+    let server = ServerBuilder::default().build();
+    let (client, target) = add_target(&server, "Target1");
+    // the artifact takes a long time to be served
+    target.push_deployment(
+        DeploymentBuilder::new("10", Type::Forced, Type::Attempt)
+            .chunk_with_mock(
+                ChunkProtocol::HTTP,
+                "app",
+                "1.0",
+                "some-chunk",
+                vec![(
+                    artifact_path(),
+                    "5eb63bbbe01eeed093cb22bb8f5acdc3",
+                    "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed",
+                    "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9",
+                )],
+                Box::new(|when: When, then: Then| {
+                    when.method(GET).path("/download/test.txt");
+                    then.status(200)
+                        .delay(Duration::from_secs(30))
+                        .body("hello world");
+                }),
+            )
+            .build(),
+    );
+
+    let reply = client.poll().await.expect("poll failed");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+
+    // partial files are removed when the download is cancelled
+    let out_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    let part = out_dir.path().join("some-chunk").join("test.txt.part");
+    std::fs::create_dir_all(part.parent().unwrap()).expect("failed to create dir");
+    std::fs::write(&part, "hello").expect("failed to write part file");
+
+    let token = CancellationToken::new();
+    let options = DownloadOptions::default().cancellation_token(token.clone());
+    let download = update.download_with(out_dir.path(), &options);
+    let cancel = async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        token.cancel();
+    };
+    let (result, _) = future::join(download, cancel).await;
+    assert_matches!(result, Err(Error::Canceled));
+    assert!(!part.exists());
+
+    // the download is interrupted once the server cancels the update
+    target.cancel_action("10");
+    let canceled =
+        target.expect_cancel_feedback("10", Execution::Canceled, Finished::Success, vec![]);
+    let options = DownloadOptions::default();
+    let download = update.download_or_cancel(&client, out_dir.path(), &options);
+    let result = tokio::time::timeout(Duration::from_secs(10), download)
+        .await
+        .expect("download has not been interrupted");
+    assert_matches!(result, Err(Error::Canceled));
+    assert_eq!(canceled.calls(), 1);
+    assert!(update.cancellation_token().is_cancelled());
+    assert!(!out_dir.path().join("some-chunk").join("test.txt").exists());
+}
+
+#[tokio::test]
+async fn download_to() {
+    use assert_matches::assert_matches;
+
+    init();
+
+    let server = ServerBuilder::default().build();
+    let (client, target) = add_target(&server, "Target1");
+    target.push_deployment(get_deployment(false, true));
+
+    let reply = client.poll().await.expect("poll failed");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+    let chunk = update.chunks().next().unwrap();
+    let art = chunk.artifacts().next().unwrap();
+
+    let mut sink: Vec<u8> = Vec::new();
+    let written = art.download_to(&mut sink).await.expect("download failed");
+    assert_eq!(written, 11);
+    assert_eq!(sink, b"hello world");
+
+    // the size is verified
+    target.push_deployment(
+        DeploymentBuilder::new("11", Type::Forced, Type::Attempt)
+            .chunk(
+                ChunkProtocol::BOTH,
+                "app",
+                "1.0",
+                "some-chunk",
+                vec![(
+                    artifact_path(),
+                    "5eb63bbbe01eeed093cb22bb8f5acdc3",
+                    "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed",
+                    "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9",
+                )],
+            )
+            .artifact_size("test.txt", 12)
+            .build(),
+    );
+    let reply = client.poll().await.expect("poll failed");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+    let chunk = update.chunks().next().unwrap();
+    let art = chunk.artifacts().next().unwrap();
+    assert_matches!(
+        art.download_to(&mut Vec::new()).await,
+        Err(Error::SizeMismatch {
+            expected: 12,
+            received: 11
+        })
+    );
+
+    // the hashes are verified
+    target.push_deployment(get_deployment(false, false));
+    let reply = client.poll().await.expect("poll failed");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+    let chunk = update.chunks().next().unwrap();
+    let art = chunk.artifacts().next().unwrap();
+    let result = art.download_to(&mut Vec::new()).await;
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "hash-digest")] {
+            assert_matches!(result, Err(Error::ChecksumError(_)));
+        } else {
+            assert_matches!(result, Ok(11));
+        }
+    }
+}
+
+#[tokio::test]
+async fn download_stream() {
+    #[cfg(feature = "hash-digest")]
+    use hawkbit::ddi::ChecksumType;
+    use hawkbit::ddi::VerificationPolicy;
+
+    init();
+
+    let server = ServerBuilder::default().build();
+    let (client, target) = add_target(&server, "Target1");
+
+    target.push_deployment(get_deployment(false, true));
+    let reply = client.poll().await.expect("poll failed");
+
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+    let chunk = update.chunks().next().unwrap();
+    let art = chunk.artifacts().next().unwrap();
+
+    async fn check_download(mut stream: Box<dyn Stream<Item = Result<Bytes, Error>> + Unpin>) {
+        let mut downloaded: Vec<u8> = Vec::new();
+        while let Some(b) = stream.next().await {
+            downloaded.extend(b.unwrap().as_ref());
+        }
+
+        // Compare downloaded content with the actual file
+        let mut art_file = File::open(artifact_path()).expect("failed to open artifact");
+        let mut expected = Vec::new();
+        art_file
+            .read_to_end(&mut expected)
+            .expect("failed to read artifact");
+
+        assert_eq!(downloaded, expected);
+    }
+
+    // Download artifact using the stream API
+    let stream = art
+        .download_stream()
+        .await
+        .expect("failed to get download stream");
+    check_download(Box::new(stream)).await;
+
+    let policies = [
+        VerificationPolicy::SizeOnly,
+        VerificationPolicy::Strongest,
+        VerificationPolicy::All,
+        #[cfg(feature = "hash-md5")]
+        VerificationPolicy::Require(ChecksumType::Md5),
+        #[cfg(feature = "hash-sha1")]
+        VerificationPolicy::Require(ChecksumType::Sha1),
+        #[cfg(feature = "hash-sha256")]
+        VerificationPolicy::Require(ChecksumType::Sha256),
+    ];
+
+    for policy in policies {
+        let stream = art
+            .download_stream_verified(policy)
+            .await
+            .expect("failed to get download stream");
+        check_download(Box::new(stream)).await;
+    }
+}
+
+#[cfg(feature = "hash-digest")]
+#[tokio::test]
+async fn wrong_checksums() {
+    use assert_matches::assert_matches;
+    use hawkbit::ddi::{ChecksumType, VerificationPolicy};
+
+    init();
+
+    let server = ServerBuilder::default().build();
+    let (client, target) = add_target(&server, "Target1");
+
+    target.push_deployment(get_deployment(false, false));
+    let reply = client.poll().await.expect("poll failed");
+
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+    let chunk = update.chunks().next().unwrap();
+    let art = chunk.artifacts().next().unwrap();
+
+    let out_dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    let result = art.download(out_dir.path()).await;
+
+    // the corrupted file is never given its final name
+    assert_matches!(result, Err(Error::ChecksumError(_)));
+    let part_file = out_dir.path().join("test.txt.part");
+    assert!(!part_file.exists());
+    let file = out_dir.path().join("test.txt");
+    assert!(!file.exists());
+
+    let stream = art
+        .download_stream()
+        .await
+        .expect("failed to get download stream");
+    let end = stream.skip_while(|b| future::ready(b.is_ok())).next().await;
+    assert_matches!(end, Some(Err(Error::ChecksumError(_))));
+
+    let checksums = [
+        #[cfg(feature = "hash-md5")]
+        ChecksumType::Md5,
+        #[cfg(feature = "hash-sha1")]
+        ChecksumType::Sha1,
+        #[cfg(feature = "hash-sha256")]
+        ChecksumType::Sha256,
+    ];
+
+    for checksum in checksums {
+        let stream = art
+            .download_stream_verified(VerificationPolicy::Require(checksum))
+            .await
+            .expect("failed to get download stream");
+        let end = stream.skip_while(|b| future::ready(b.is_ok())).next().await;
+        assert_matches!(end, Some(Err(Error::ChecksumError(c))) if c == checksum);
+    }
+
+    // checksums are not verified if disabled by the policy of the client
+    let client = Client::builder(&server.base_url(), &server.tenant, &target.name)
+        .authorization(target.client_auth.clone())
+        .verification_policy(VerificationPolicy::SizeOnly)
+        .build()
+        .expect("DDI creation failed");
+    let reply = client.poll().await.expect("poll failed");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+    let chunk = update.chunks().next().unwrap();
+    let art = chunk.artifacts().next().unwrap();
+    let downloaded = art
+        .download(out_dir.path())
+        .await
+        .expect("failed to download artifact");
+    assert_matches!(
+        downloaded.verify(&VerificationPolicy::Strongest).await,
+        Err(Error::ChecksumError(_))
+    );
+}
+
+#[tokio::test]
+async fn cancel_action() {
+    init();
+
+    let server = ServerBuilder::default().build();
+    let (client, target) = add_target(&server, "Target1");
+    target.cancel_action("10");
+
+    let reply = client.poll().await.expect("poll failed");
+    assert!(reply.config_data_request().is_none());
+    assert!(reply.update().is_none());
+    let cancel_action = reply.cancel_action().expect("missing cancel action");
+
+    let id = cancel_action
+        .id()
+        .await
+        .expect("failed to fetch cancel action id");
+    assert_eq!(id, "10");
+
+    assert_eq!(target.poll_hits(), 1);
+    assert_eq!(target.cancel_action_hits(), 1);
+
+    let mut mock = target.expect_cancel_feedback(
+        &id,
+        Execution::Proceeding,
+        Finished::None,
+        vec!["Cancelling"],
+    );
+    assert_eq!(mock.calls(), 0);
+
+    cancel_action
+        .send_feedback(Execution::Proceeding, Finished::None, vec!["Cancelling"])
+        .await
+        .expect("Failed to send feedback");
+    assert_eq!(mock.calls(), 1);
+    mock.delete();
+    // the details of the cancel action are only fetched once
+    assert_eq!(target.cancel_action_hits(), 1);
+}
+
+#[tokio::test]
+async fn cancel_info() {
+    init();
+
+    let server = ServerBuilder::default().build();
+    let (client, target) = add_target(&server, "Target1");
+    target.cancel_action("10");
+
+    let reply = client.poll().await.expect("poll failed");
+    let cancel_action = reply.cancel_action().expect("missing cancel action");
+    let info = cancel_action
+        .fetch()
+        .await
+        .expect("failed to fetch cancel action");
+    assert_eq!(info.id(), "10");
+    assert_eq!(info.stop_id(), "10");
+
+    let progress = target.expect_cancel_feedback(
+        "10",
+        Execution::Proceeding,
+        Finished::None,
+        vec!["Cancelling"],
+    );
+    info.report_progress(vec!["Cancelling"])
+        .await
+        .expect("Failed to send feedback");
+    assert_eq!(progress.calls(), 1);
+
+    let rejected = target.expect_cancel_feedback(
+        "10",
+        Execution::Rejected,
+        Finished::Failure,
+        vec!["Already installing"],
+    );
+    info.clone()
+        .reject("Already installing")
+        .await
+        .expect("Failed to send feedback");
+    assert_eq!(rejected.calls(), 1);
+
+    let accepted = target.expect_cancel_feedback(
+        "10",
+        Execution::Canceled,
+        Finished::Success,
+        vec!["Cancelled"],
+    );
+    info.accept(vec!["Cancelled"])
+        .await
+        .expect("Failed to send feedback");
+    assert_eq!(accepted.calls(), 1);
+
+    cancel_action.id().await.expect("failed to fetch cancel id");
+    assert_eq!(target.cancel_action_hits(), 1);
+}
+
+#[tokio::test]
+async fn client_authorization() {
+    init();
+
+    let server = ServerBuilder::default()
+        .target_authorization(hawkbit_mock::ddi::TargetAuthorization::None)
+        .build();
+    let (client, _target) = add_target(&server, "Target1");
+
+    client.poll().await.expect("poll failed");
+
+    let server = ServerBuilder::default()
+        .target_authorization(hawkbit_mock::ddi::TargetAuthorization::TargetToken)
+        .build();
+    let _ = add_target(&server, "Target2");
+    // create the client manually to control the authorization method
+    let client1 = Client::new(
+        &server.base_url(),
+        &server.tenant,
+        "Target2",
+        hawkbit::ddi::ClientAuthorization::TargetToken("KeyTarget2".to_string()),
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    let client2 = Client::new(
+        &server.base_url(),
+        &server.tenant,
+        "Target2",
+        hawkbit::ddi::ClientAuthorization::TargetToken("WrongToken".to_string()),
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    let client3 = Client::new(
+        &server.base_url(),
+        &server.tenant,
+        "Target2",
+        hawkbit::ddi::ClientAuthorization::None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    client1.poll().await.expect("poll failed");
+    client2
+        .poll()
+        .await
+        .expect_err("poll with wrong token succeeded");
+    client3
+        .poll()
+        .await
+        .expect_err("poll without token succeeded");
+
+    let server = ServerBuilder::default()
+        .target_authorization(hawkbit_mock::ddi::TargetAuthorization::GatewayToken)
+        .build();
+    let _ = add_target(&server, "Target3");
+    // create the client manually to control the authorization method
+    let client1 = Client::new(
+        &server.base_url(),
+        &server.tenant,
+        "Target3",
+        hawkbit::ddi::ClientAuthorization::GatewayToken("KeyDEFAULT".to_string()),
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    let client2 = Client::new(
+        &server.base_url(),
+        &server.tenant,
+        "Target3",
+        hawkbit::ddi::ClientAuthorization::GatewayToken("WrongToken".to_string()),
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    let client3 = Client::new(
+        &server.base_url(),
+        &server.tenant,
+        "Target3",
+        hawkbit::ddi::ClientAuthorization::TargetToken("KeyTarget3".to_string()),
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    client1.poll().await.expect("poll failed");
+    client2
+        .poll()
+        .await
+        .expect_err("poll with wrong token succeeded");
+    client3
+        .poll()
+        .await
+        .expect_err("poll with TargetToken succeeded");
+}
+
+#[tokio::test]
+async fn certificate() {
+    init();
+
+    // the mock server used by hawkbit_mock does not support https, so we
+    // cannot test it fully, but we can at least test loading a certificate
+    // file.
+    let server = ServerBuilder::default()
+        .target_authorization(hawkbit_mock::ddi::TargetAuthorization::None)
+        .build();
+    let (_client, _target) = add_target(&server, "Target1");
+    let client1 = Client::new(
+        &server.base_url(),
+        &server.tenant,
+        "Target1",
+        hawkbit::ddi::ClientAuthorization::TargetToken("KeyTarget1".to_string()),
+        Some("tests/data/test.txt"),
+        None,
         None,
     )
     .unwrap();
 
-    client1.poll().await.expect("poll failed");
-    client2
+    // this returns an error, as the mock server does not use https
+    client1.poll().await.unwrap_err();
+}
+
+#[tokio::test]
+async fn client_builder() {
+    init();
+
+    let server = httpmock::MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/DEFAULT/controller/v1/Target1")
+            .header("Authorization", "TargetToken KeyTarget1")
+            .header("User-Agent", "hawkbit-test/1.0")
+            .header("X-Device-Serial", "1234");
+
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"config": {"polling": {"sleep": "00:00:30"}}}));
+    });
+
+    let client = Client::builder(&server.base_url(), "DEFAULT", "Target1")
+        .authorization(hawkbit::ddi::ClientAuthorization::TargetToken(
+            "KeyTarget1".to_string(),
+        ))
+        .user_agent("hawkbit-test/1.0")
+        .default_header(
+            reqwest::header::HeaderName::from_static("x-device-serial"),
+            reqwest::header::HeaderValue::from_static("1234"),
+        )
+        .connect_timeout(Duration::from_secs(5))
+        .read_timeout(Duration::from_secs(5))
+        .timeout(Duration::from_secs(30))
+        .build()
+        .expect("DDI creation failed");
+
+    let reply = client.poll().await.expect("poll failed");
+    assert_eq!(reply.polling_sleep().unwrap(), Duration::from_secs(30));
+    assert_eq!(mock.calls(), 1);
+}
+
+#[tokio::test]
+async fn client_builder_certificate() {
+    init();
+
+    let server = ServerBuilder::default()
+        .target_authorization(hawkbit_mock::ddi::TargetAuthorization::None)
+        .build();
+    let (_client, _target) = add_target(&server, "Target1");
+
+    // certificates can be passed from memory rather than from files
+    let cert = std::fs::read(artifact_path()).unwrap();
+    let client = Client::builder(&server.base_url(), &server.tenant, "Target1")
+        .root_certificates_pem_bundle(&cert)
+        .tls_built_in_root_certs(false)
+        .https_only(true)
+        .build()
+        .unwrap();
+
+    // this returns an error, as the mock server does not use https
+    client.poll().await.unwrap_err();
+
+    // invalid client identities are reported when building the client
+    Client::builder(&server.base_url(), &server.tenant, "Target1")
+        .identity_pem(b"not a pem")
+        .build()
+        .unwrap_err();
+}
+
+#[tokio::test]
+async fn auth_provider_refresh() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::future::BoxFuture;
+    use hawkbit::ddi::AuthProvider;
+    use reqwest::header::HeaderValue;
+
+    init();
+
+    // Provider switching to a new token when refreshed
+    #[derive(Debug, Default)]
+    struct RotatingToken {
+        refreshed: AtomicUsize,
+    }
+
+    impl AuthProvider for RotatingToken {
+        fn authorization(&self) -> BoxFuture<'_, Result<Option<HeaderValue>, Error>> {
+            Box::pin(async move {
+                let token = match self.refreshed.load(Ordering::SeqCst) {
+                    0 => "TargetToken old",
+                    _ => "TargetToken new",
+                };
+                Ok(Some(HeaderValue::from_static(token)))
+            })
+        }
+
+        fn refresh(&self) -> BoxFuture<'_, Result<bool, Error>> {
+            Box::pin(async move {
+                self.refreshed.fetch_add(1, Ordering::SeqCst);
+                Ok(true)
+            })
+        }
+    }
+
+    let server = httpmock::MockServer::start();
+    let accepted = server.mock(|when, then| {
+        when.method(GET)
+            .path("/DEFAULT/controller/v1/Target1")
+            .header("Authorization", "TargetToken new");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"config": {"polling": {"sleep": "00:01:00"}}}));
+    });
+    let rejected = server.mock(|when, then| {
+        when.method(GET)
+            .path("/DEFAULT/controller/v1/Target1")
+            .header_not("Authorization", "TargetToken new");
+        then.status(401);
+    });
+
+    let client = Client::builder(&server.base_url(), "DEFAULT", "Target1")
+        .auth_provider(RotatingToken::default())
+        .build()
+        .expect("DDI creation failed");
+
+    client.poll().await.expect("poll failed");
+    assert_eq!(rejected.calls(), 1);
+    assert_eq!(accepted.calls(), 1);
+
+    // the new token is used right away for the following requests
+    client.poll().await.expect("poll failed");
+    assert_eq!(rejected.calls(), 1);
+    assert_eq!(accepted.calls(), 2);
+}
+
+#[tokio::test]
+async fn file_token() {
+    use hawkbit::ddi::FileToken;
+
+    init();
+
+    let server = httpmock::MockServer::start();
+    let accepted = server.mock(|when, then| {
+        when.method(GET)
+            .path("/DEFAULT/controller/v1/Target1")
+            .header("Authorization", "TargetToken new");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({"config": {"polling": {"sleep": "00:01:00"}}}));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/DEFAULT/controller/v1/Target1")
+            .header_not("Authorization", "TargetToken new");
+        then.status(401);
+    });
+
+    let dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    let token_file = dir.path().join("token");
+    std::fs::write(&token_file, "old\n").unwrap();
+
+    let client = Client::builder(&server.base_url(), "DEFAULT", "Target1")
+        .auth_provider(FileToken::target_token(&token_file))
+        .build()
+        .expect("DDI creation failed");
+
+    client
         .poll()
         .await
-        .expect_err("poll with wrong token succeeded");
-    client3
-        .poll()
+        .expect_err("poll with old token succeeded");
+    assert_eq!(accepted.calls(), 0);
+
+    // rotate the token, the client picks it up without being re-created
+    std::fs::write(&token_file, "new\n").unwrap();
+    client.poll().await.expect("poll failed");
+    assert_eq!(accepted.calls(), 1);
+}
+
+#[tokio::test]
+async fn http_errors() {
+    use assert_matches::assert_matches;
+    use hawkbit::ddi::Endpoint;
+    use reqwest::StatusCode;
+
+    init();
+
+    let server = httpmock::MockServer::start();
+    let client = Client::builder(&server.base_url(), "DEFAULT", "Target1")
+        .build()
+        .expect("DDI creation failed");
+
+    for (status, retryable) in &[(401, false), (404, false), (409, false), (503, true)] {
+        let mut mock = server.mock(|when, then| {
+            when.method(GET).path("/DEFAULT/controller/v1/Target1");
+            then.status(*status).body("something went wrong");
+        });
+
+        let err = client.poll().await.expect_err("poll succeeded");
+        assert_eq!(err.status(), StatusCode::from_u16(*status).ok());
+        assert_eq!(err.is_retryable(), *retryable);
+
+        match status {
+            401 => assert_matches!(
+                err,
+                Error::Unauthorized {
+                    endpoint: Endpoint::Poll,
+                    status: StatusCode::UNAUTHORIZED,
+                    ..
+                }
+            ),
+            404 => assert_matches!(
+                err,
+                Error::NotFound {
+                    endpoint: Endpoint::Poll,
+                    ..
+                }
+            ),
+            409 => assert_matches!(
+                err,
+                Error::Conflict {
+                    endpoint: Endpoint::Poll,
+                    ..
+                }
+            ),
+            _ => assert_matches!(
+                err,
+                Error::ServerUnavailable { endpoint: Endpoint::Poll, body: Some(body), .. } if body == "something went wrong"
+            ),
+        }
+
+        mock.delete();
+    }
+
+    // the endpoint kind is reported for other requests as well
+    let server = ServerBuilder::default().build();
+    let (client, target) = add_target(&server, "Target1");
+    target.push_deployment(get_deployment(false, true));
+
+    let reply = client.poll().await.expect("poll failed");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+    let err = update
+        .send_feedback(Execution::Proceeding, Finished::None, vec![])
         .await
-        .expect_err("poll with TargetToken succeeded");
+        .expect_err("unexpected feedback accepted");
+    assert_matches!(
+        err,
+        Error::NotFound {
+            endpoint: Endpoint::Feedback,
+            ..
+        }
+    );
 }
 
 #[tokio::test]
-async fn certificate() {
+async fn retry_policy() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use assert_matches::assert_matches;
+    use hawkbit::ddi::RetryPolicy;
+    use httpmock::HttpMockResponse;
+
     init();
 
-    // the mock server used by hawkbit_mock does not support https, so we
-    // cannot test it fully, but we can at least test loading a certificate
-    // file.
-    let server = ServerBuilder::default()
-        .target_authorization(hawkbit_mock::ddi::TargetAuthorization::None)
-        .build();
-    let (_client, _target) = add_target(&server, "Target1");
-    let client1 = Client::new(
-        &server.base_url(),
-        &server.tenant,
-        "Target1",
-        hawkbit::ddi::ClientAuthorization::TargetToken("KeyTarget1".to_string()),
-        Some("tests/data/test.txt"),
+    let policy = RetryPolicy::default()
+        .initial_backoff(Duration::from_millis(10))
+        .max_attempts(3);
+
+    // the server is unavailable for the first two polls
+    let server = httpmock::MockServer::start();
+    let polls = Arc::new(AtomicUsize::new(0));
+    let counter = polls.clone();
+    server.mock(|when, then| {
+        when.method(GET).path("/DEFAULT/controller/v1/Target1");
+        then.respond_with(move |_| {
+            if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                HttpMockResponse::builder()
+                    .status(503)
+                    .header("Retry-After", "0")
+                    .build()
+            } else {
+                HttpMockResponse::builder()
+                    .status(200)
+                    .header("Content-Type", "application/json")
+                    .body(r#"{"config": {"polling": {"sleep": "00:01:00"}}}"#)
+                    .build()
+            }
+        });
+    });
+
+    let client = Client::builder(&server.base_url(), "DEFAULT", "Target1")
+        .retry_policy(policy.clone())
+        .build()
+        .expect("DDI creation failed");
+    client.poll().await.expect("poll failed");
+    assert_eq!(polls.load(Ordering::SeqCst), 3);
+
+    // give up after the maximum number of attempts
+    polls.store(0, Ordering::SeqCst);
+    let client = Client::builder(&server.base_url(), "DEFAULT", "Target1")
+        .retry_policy(policy.clone().max_attempts(2))
+        .build()
+        .expect("DDI creation failed");
+    assert_matches!(
+        client.poll().await,
+        Err(Error::ServerUnavailable {
+            retry_after: Some(_),
+            ..
+        })
+    );
+    assert_eq!(polls.load(Ordering::SeqCst), 2);
+
+    // confirmations are not replayed on server errors as they may have been processed
+    let server = httpmock::MockServer::start();
+    let confirmation_url = server.url("/DEFAULT/controller/v1/Target1/confirmationBase/10");
+    server.mock(|when, then| {
+        when.method(GET).path("/DEFAULT/controller/v1/Target1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({
+                "config": {"polling": {"sleep": "00:01:00"}},
+                "_links": {"confirmationBase": {"href": confirmation_url}}
+            }));
+    });
+    let feedback = server.mock(|when, then| {
+        when.method(httpmock::Method::POST)
+            .path("/DEFAULT/controller/v1/Target1/confirmationBase/10/feedback");
+        then.status(500);
+    });
+
+    let client = Client::builder(&server.base_url(), "DEFAULT", "Target1")
+        .retry_policy(policy)
+        .build()
+        .expect("DDI creation failed");
+    let reply = client.poll().await.expect("poll failed");
+    let confirmation = reply
+        .confirmation_base()
+        .expect("missing confirmation request");
+    confirmation
+        .confirm()
+        .await
+        .expect_err("confirmation succeeded");
+    assert_eq!(feedback.calls(), 1);
+}
+
+#[tokio::test]
+async fn feedback_queue() {
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use hawkbit::ddi::FeedbackQueue;
+    use httpmock::HttpMockResponse;
+
+    init();
+
+    let server = httpmock::MockServer::start();
+    let deployment_url = server.url("/DEFAULT/controller/v1/Target1/deploymentBase/10");
+    server.mock(|when, then| {
+        when.method(GET).path("/DEFAULT/controller/v1/Target1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({
+                "config": {"polling": {"sleep": "00:01:00"}},
+                "_links": {"deploymentBase": {"href": deployment_url}}
+            }));
+    });
+    server.mock(|when, then| {
+        when.method(GET)
+            .path("/DEFAULT/controller/v1/Target1/deploymentBase/10");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(json!({
+                "id": "10",
+                "deployment": {"download": "forced", "update": "forced", "chunks": []}
+            }));
+    });
+
+    // the server only receives feedback while replying 200
+    let reply_status = Arc::new(AtomicU16::new(200));
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let (feedback_status, bodies) = (reply_status.clone(), received.clone());
+    server.mock(|when, then| {
+        when.method(httpmock::Method::POST)
+            .path("/DEFAULT/controller/v1/Target1/deploymentBase/10/feedback");
+        then.respond_with(move |req| {
+            let status = feedback_status.load(Ordering::SeqCst);
+            if status == 200 {
+                let body: serde_json::Value = serde_json::from_slice(req.body_ref()).unwrap();
+                bodies.lock().unwrap().push(body);
+            }
+            HttpMockResponse::builder().status(status).build()
+        });
+    });
+    let online =
+        |online: bool| reply_status.store(if online { 200 } else { 503 }, Ordering::SeqCst);
+
+    let dir = TempDir::new("test-hawkbitrs").expect("Failed to create temp dir");
+    let queue_path = dir.path().join("feedback.json");
+    let new_client = || {
+        let queue = FeedbackQueue::open(&queue_path).expect("failed to open queue");
+        Client::builder(&server.base_url(), "DEFAULT", "Target1")
+            .feedback_queue(queue)
+            .build()
+            .expect("DDI creation failed")
+    };
+    let status = |body: &serde_json::Value| {
+        let status = &body["status"];
+        (
+            status["execution"].as_str().unwrap().to_string(),
+            status["details"][0]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        )
+    };
+
+    let client = new_client();
+    let reply = client.poll().await.expect("poll failed");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+
+    // feedback is queued while offline, superseded progress messages are dropped
+    online(false);
+    for details in ["downloading", "installing"] {
+        update
+            .send_feedback(Execution::Proceeding, Finished::None, vec![details])
+            .await
+            .expect("feedback not queued");
+    }
+    update
+        .send_feedback(Execution::Closed, Finished::Success, vec!["installed"])
+        .await
+        .expect("feedback not queued");
+    assert!(received.lock().unwrap().is_empty());
+    assert_matches::assert_matches!(
+        client.flush_feedback().await,
+        Err(Error::ServerUnavailable { .. })
+    );
+
+    // the queue survives a restart and is sent after the next successful poll
+    drop(client);
+    let client = new_client();
+    online(true);
+    client.poll().await.expect("poll failed");
+    {
+        let mut received = received.lock().unwrap();
+        let statuses: Vec<_> = received.iter().map(status).collect();
+        assert_eq!(
+            statuses,
+            vec![("closed".to_string(), "installed".to_string())]
+        );
+        received.clear();
+    }
+
+    // queued messages are sent before new ones
+    let reply = client.poll().await.expect("poll failed");
+    let update = reply.update().expect("missing update");
+    let update = update.fetch().await.expect("failed to fetch update info");
+    online(false);
+    update
+        .send_feedback(Execution::Proceeding, Finished::None, vec!["downloading"])
+        .await
+        .expect("feedback not queued");
+    online(true);
+    update
+        .send_feedback(Execution::Closed, Finished::Failure, vec!["failed"])
+        .await
+        .expect("failed to send feedback");
+    let statuses: Vec<_> = received.lock().unwrap().iter().map(status).collect();
+    assert_eq!(
+        statuses,
+        vec![
+            ("proceeding".to_string(), "downloading".to_string()),
+            ("closed".to_string(), "failed".to_string())
+        ]
+    );
+    client.flush_feedback().await.expect("flush failed");
+    received.lock().unwrap().clear();
+
+    // a final result rejected by the server is dropped so it does not block later feedback
+    online(false);
+    update
+        .send_feedback(Execution::Closed, Finished::Success, vec!["installed"])
+        .await
+        .expect("feedback not queued");
+    reply_status.store(400, Ordering::SeqCst);
+    assert_matches::assert_matches!(
+        client.flush_feedback().await,
+        Err(Error::HttpStatus { status, .. }) if status == 400
+    );
+    online(true);
+    client.flush_feedback().await.expect("flush failed");
+    assert!(received.lock().unwrap().is_empty());
+
+    // and its error is returned when it is sent with new feedback
+    online(false);
+    update
+        .send_feedback(Execution::Closed, Finished::Success, vec!["installed"])
+        .await
+        .expect("feedback not queued");
+    reply_status.store(401, Ordering::SeqCst);
+    assert_matches::assert_matches!(
+        update
+            .send_feedback(Execution::Proceeding, Finished::None, vec!["rebooting"])
+            .await,
+        Err(Error::Unauthorized { .. })
+    );
+    online(true);
+    update
+        .send_feedback(Execution::Closed, Finished::Success, vec!["rebooted"])
+        .await
+        .expect("failed to send feedback");
+    let statuses: Vec<_> = received.lock().unwrap().iter().map(status).collect();
+    assert_eq!(
+        statuses,
+        vec![("closed".to_string(), "rebooted".to_string())]
+    );
+    received.lock().unwrap().clear();
+
+    // but dropped once the server reports the action as closed
+    online(false);
+    update
+        .send_feedback(Execution::Closed, Finished::Success, vec!["installed"])
+        .await
+        .expect("feedback not queued");
+    reply_status.store(410, Ordering::SeqCst);
+    client.flush_feedback().await.expect("flush failed");
+    online(true);
+    client.flush_feedback().await.expect("flush failed");
+    assert!(received.lock().unwrap().is_empty());
+}
+
+#[derive(Default)]
+struct TestHandler {
+    configs: usize,
+    confirmations: Vec<String>,
+    updates: Vec<String>,
+    cancels: Vec<String>,
+    fail_update: bool,
+    wait_cancel: bool,
+    stop_on_cancel: bool,
+}
+
+/// Error returned by [`TestHandler`] when its update has been cancelled.
+const UPDATE_CANCELED: &str = "Update canceled";
+
+impl hawkbit::ddi::UpdateHandler for TestHandler {
+    type Config = serde_json::Value;
+    type Error = String;
+
+    async fn on_config_request(&mut self) -> serde_json::Value {
+        self.configs += 1;
+        json!({"awesome": true})
+    }
+
+    async fn on_confirmation(
+        &mut self,
+        info: &hawkbit::ddi::ConfirmationInfo,
+    ) -> ConfirmationResponse {
+        self.confirmations.push(info.action_id().to_string());
+        ConfirmationResponse::Confirmed
+    }
+
+    async fn on_update(&mut self, update: &hawkbit::ddi::Update) -> Result<(), String> {
+        self.updates.push(update.action_id().to_string());
+        if self.wait_cancel {
+            update.cancellation_token().cancelled().await;
+            if self.stop_on_cancel {
+                return Err(UPDATE_CANCELED.to_string());
+            }
+        }
+        if self.fail_update {
+            Err("Installation failed".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn is_interrupted(&self, error: &String) -> bool {
+        error == UPDATE_CANCELED
+    }
+
+    async fn on_cancel(&mut self, action_id: &str) -> bool {
+        self.cancels.push(action_id.to_string());
+        true
+    }
+}
+
+#[tokio::test]
+async fn agent() {
+    use hawkbit::ddi::Agent;
+
+    init();
+
+    let server = ServerBuilder::default().build();
+    let (client, target) = add_target(&server, "Target1");
+    let mut agent = Agent::new(client, TestHandler::default());
+
+    // nothing to do
+    let sleep = agent.poll_once().await.expect("poll failed");
+    assert_eq!(sleep, Duration::from_secs(60));
+    assert_eq!(target.poll_hits(), 1);
+
+    // config data is uploaded
+    target.request_config(json!({
+        "mode" : null,
+        "data" : {
+            "awesome" : true,
+        },
+        "status" : {
+            "result" : {
+            "finished" : "success"
+            },
+            "execution" : "closed",
+            "details" : []
+        }
+    }));
+    agent.poll_once().await.expect("poll failed");
+    assert_eq!(agent.handler().configs, 1);
+    assert_eq!(target.config_data_hits(), 1);
+
+    // update is deployed and its status reported
+    let deploy = get_deployment(false, true);
+    target.push_deployment(deploy);
+    let proceeding = target.expect_deployment_feedback(
+        "10",
+        Execution::Proceeding,
+        Finished::None,
         None,
+        vec![],
+    );
+    let closed =
+        target.expect_deployment_feedback("10", Execution::Closed, Finished::Success, None, vec![]);
+    agent.poll_once().await.expect("poll failed");
+    assert_eq!(agent.handler().updates, vec!["10"]);
+    assert_eq!(proceeding.calls(), 1);
+    assert_eq!(closed.calls(), 1);
+
+    // failed updates are reported as well
+    agent.handler_mut().fail_update = true;
+    let failed = target.expect_deployment_feedback(
+        "10",
+        Execution::Closed,
+        Finished::Failure,
         None,
-    )
-    .unwrap();
+        vec!["Installation failed"],
+    );
+    agent.poll_once().await.expect("poll failed");
+    assert_eq!(failed.calls(), 1);
+
+    // cancel action is accepted
+    target.cancel_action("5");
+    let cancelled =
+        target.expect_cancel_feedback("5", Execution::Canceled, Finished::Success, vec![]);
+    agent.poll_once().await.expect("poll failed");
+    assert_eq!(agent.handler().cancels, vec!["5"]);
+    assert_eq!(cancelled.calls(), 1);
+}
 
-    // this returns an error, as the mock server does not use https
-    client1.poll().await.unwrap_err();
+#[tokio::test]
+async fn agent_confirmation_and_shutdown() {
+    use hawkbit::ddi::Agent;
+
+    init();
+
+    let server = ServerBuilder::default().build();
+    let (client, target) = add_target(&server, "Target1");
+    target.push_deployment(get_deployment(true, true));
+    let confirmed =
+        target.expect_confirmation_feedback("10", Some(1), ConfirmationResponse::Confirmed, vec![]);
+
+    // the pending request is fully handled even if the shutdown is immediate
+    let mut agent = Agent::new(client, TestHandler::default());
+    agent.run_until(future::ready(())).await;
+
+    assert_eq!(target.poll_hits(), 1);
+    assert_eq!(agent.handler().confirmations, vec!["10"]);
+    assert_eq!(confirmed.calls(), 1);
+}
+
+#[tokio::test]
+async fn agent_polling_policy_and_trigger() {
+    use hawkbit::ddi::{Agent, PollingPolicy};
+
+    init();
+
+    let server = ServerBuilder::default().polling_sleep("01:00:00").build();
+    let (client, target) = add_target(&server, "Target1");
+
+    let policy = PollingPolicy::default().max_interval(Duration::from_secs(600));
+    let mut agent = Agent::new(client, TestHandler::default()).polling_policy(policy);
+    assert_eq!(agent.poll_once().await.unwrap(), Duration::from_secs(600));
+    assert_eq!(target.poll_hits(), 1);
+
+    // the trigger wakes up the agent without waiting for the polling interval
+    let trigger = agent.poll_trigger();
+    let shutdown = async {
+        trigger.poll_now();
+        while target.poll_hits() < 3 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(10), agent.run_until(shutdown))
+        .await
+        .expect("agent did not poll again");
+}
+
+#[tokio::test]
+async fn agent_cancel_update() {
+    use hawkbit::ddi::Agent;
+    use tokio_util::sync::CancellationToken;
+
+    init();
+
+    let server = ServerBuilder::default().polling_sleep("00:00:00").build();
+    let (client, target) = add_target(&server, "Target1");
+    target.push_deployment(get_deployment(false, true));
+    target.cancel_action("10");
+    let _proceeding = target.expect_deployment_feedback(
+        "10",
+        Execution::Proceeding,
+        Finished::None,
+        None,
+        vec![],
+    );
+    let handler = TestHandler {
+        wait_cancel: true,
+        stop_on_cancel: true,
+        fail_update: true,
+        ..Default::default()
+    };
+    let mut agent = Agent::new(client, handler);
+
+    // the handler is interrupted and the cancellation confirmed
+    let canceled =
+        target.expect_cancel_feedback("10", Execution::Canceled, Finished::Success, vec![]);
+    let failed = target.expect_deployment_feedback(
+        "10",
+        Execution::Closed,
+        Finished::Failure,
+        None,
+        vec!["Installation failed"],
+    );
+    tokio::time::timeout(Duration::from_secs(10), agent.poll_once())
+        .await
+        .expect("update has not been cancelled")
+        .expect("poll failed");
+    assert_eq!(canceled.calls(), 1);
+    assert_eq!(failed.calls(), 0);
+    assert!(agent.handler().cancels.is_empty());
+
+    // the cancellation is rejected if the handler fails for another reason, which is reported
+    agent.handler_mut().stop_on_cancel = false;
+    let rejected = target.expect_cancel_feedback(
+        "10",
+        Execution::Rejected,
+        Finished::Failure,
+        vec!["Action cannot be canceled anymore"],
+    );
+    tokio::time::timeout(Duration::from_secs(10), agent.poll_once())
+        .await
+        .expect("update has not been cancelled")
+        .expect("poll failed");
+    assert_eq!(rejected.calls(), 1);
+    assert_eq!(failed.calls(), 1);
+    assert_eq!(canceled.calls(), 1);
+
+    // or if the handler completes the update anyway
+    agent.handler_mut().fail_update = false;
+    let closed =
+        target.expect_deployment_feedback("10", Execution::Closed, Finished::Success, None, vec![]);
+    tokio::time::timeout(Duration::from_secs(10), agent.poll_once())
+        .await
+        .expect("update has not been cancelled")
+        .expect("poll failed");
+    assert_eq!(rejected.calls(), 2);
+    assert_eq!(closed.calls(), 1);
+
+    // stopping the agent interrupts the update without closing it
+    let server = ServerBuilder::default().build();
+    let (client, target) = add_target(&server, "Target2");
+    target.push_deployment(get_deployment(false, true));
+    let _proceeding = target.expect_deployment_feedback(
+        "10",
+        Execution::Proceeding,
+        Finished::None,
+        None,
+        vec![],
+    );
+    let failed = target.expect_deployment_feedback(
+        "10",
+        Execution::Closed,
+        Finished::Failure,
+        None,
+        vec!["Installation failed"],
+    );
+    let token = CancellationToken::new();
+    let handler = TestHandler {
+        wait_cancel: true,
+        stop_on_cancel: true,
+        fail_update: true,
+        ..Default::default()
+    };
+    let mut agent = Agent::new(client, handler).cancellation_token(token.clone());
+    let stop = async {
+        while target.deployment_hits() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        token.cancel();
+    };
+    tokio::time::timeout(Duration::from_secs(10), future::join(agent.run(), stop))
+        .await
+        .expect("agent did not stop");
+    assert_eq!(agent.handler().updates, vec!["10"]);
+    assert_eq!(failed.calls(), 0);
+}
+
+#[tokio::test]
+async fn events() {
+    use assert_matches::assert_matches;
+    use hawkbit::ddi::DdiEvent;
+
+    init();
+
+    let server = ServerBuilder::default().polling_sleep("00:00:00").build();
+    let (client, target) = add_target(&server, "Target1");
+    target.request_config(json!({}));
+
+    let events = client.events();
+    futures::pin_mut!(events);
+
+    // the first poll reports the polling interval and the pending requests
+    assert_matches!(events.next().await, Some(Ok(DdiEvent::ConfigRequested(_))));
+    assert_matches!(
+        events.next().await,
+        Some(Ok(DdiEvent::PollingIntervalChanged(sleep))) if sleep == Duration::from_secs(0)
+    );
+    assert_eq!(target.poll_hits(), 1);
+
+    // the config request is still pending and is not reported again
+    target.push_deployment(get_deployment(false, true));
+    assert_matches!(events.next().await, Some(Ok(DdiEvent::UpdateAvailable(_))));
+
+    target.cancel_action("10");
+    match events.next().await {
+        Some(Ok(DdiEvent::CancelRequested(cancel_action))) => {
+            assert_eq!(cancel_action.id().await.unwrap(), "10");
+        }
+        e => panic!("unexpected event {:?}", e),
+    }
+
+    target.set_polling_sleep("00:00:01");
+    assert_matches!(
+        events.next().await,
+        Some(Ok(DdiEvent::PollingIntervalChanged(sleep))) if sleep == Duration::from_secs(1)
+    );
 }
@@ -25,6 +25,7 @@
 use std::rc::Rc;
 use std::{
     cell::{Cell, RefCell},
+    collections::HashMap,
     path::PathBuf,
 };
 
@@ -62,6 +63,7 @@ pub enum TargetAuthorization {
 pub struct ServerBuilder {
     tenant: String,
     target_authorization: TargetAuthorization,
+    polling_sleep: String,
 }
 
 impl Default for ServerBuilder {
@@ -69,6 +71,7 @@ impl Default for ServerBuilder {
         Self {
             tenant: "DEFAULT".into(),
             target_authorization: TargetAuthorization::TargetToken,
+            polling_sleep: "00:01:00".into(),
         }
     }
 }
@@ -88,12 +91,20 @@ impl ServerBuilder {
         builder
     }
 
+    /// Set the polling sleep suggested to the targets, formatted as `HH:MM:SS`, default to `00:01:00`.
+    pub fn polling_sleep(self, polling_sleep: &str) -> Self {
+        let mut builder = self;
+        builder.polling_sleep = polling_sleep.to_string();
+        builder
+    }
+
     /// Create the [`Server`].
     pub fn build(self) -> Server {
         Server {
             server: Rc::new(MockServer::start()),
             tenant: self.tenant,
             target_authorization: self.target_authorization,
+            polling_sleep: self.polling_sleep,
         }
     }
 }
@@ -104,6 +115,7 @@ pub struct Server {
     pub tenant: String,
     /// The authorization method of the server.
     pub target_authorization: TargetAuthorization,
+    polling_sleep: String,
     server: Rc<MockServer>,
 }
 
@@ -124,7 +136,13 @@ impl Server {
                 ClientAuthorization::GatewayToken(format!("Key{}", self.tenant))
             }
         };
-        Target::new(name, &self.server, &self.tenant, &client_auth)
+        Target::new(
+            name,
+            &self.server,
+            &self.tenant,
+            &client_auth,
+            &self.polling_sleep,
+        )
     }
 }
 
@@ -136,6 +154,7 @@ pub struct Target {
     pub client_auth: ClientAuthorization,
     server: Rc<MockServer>,
     tenant: String,
+    polling_sleep: RefCell<String>,
     poll: Cell<usize>,
     config_data: RefCell<Option<PendingAction>>,
     confirmation: RefCell<Option<PendingAction>>,
@@ -149,13 +168,25 @@ impl Target {
         server: &Rc<MockServer>,
         tenant: &str,
         client_auth: &ClientAuthorization,
+        polling_sleep: &str,
     ) -> Self {
-        let poll = Self::create_poll(server, tenant, name, client_auth, None, None, None, None);
+        let poll = Self::create_poll(
+            server,
+            tenant,
+            name,
+            client_auth,
+            polling_sleep,
+            None,
+            None,
+            None,
+            None,
+        );
         Target {
             name: name.to_string(),
             client_auth: client_auth.clone(),
             server: server.clone(),
             tenant: tenant.to_string(),
+            polling_sleep: RefCell::new(polling_sleep.to_string()),
             poll: Cell::new(poll),
             config_data: RefCell::new(None),
             confirmation: RefCell::new(None),
@@ -164,12 +195,26 @@ impl Target {
         }
     }
 
+    /// Require the Authorization header of the target in `when`.
+    fn authorized(&self, when: When) -> When {
+        match &self.client_auth {
+            ClientAuthorization::None => when, // do not require Authorization header
+            ClientAuthorization::TargetToken(key) => {
+                when.header("Authorization", format!("TargetToken {}", key))
+            }
+            ClientAuthorization::GatewayToken(key) => {
+                when.header("Authorization", format!("GatewayToken {}", key))
+            }
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn create_poll(
         server: &MockServer,
         tenant: &str,
         name: &str,
         client_auth: &ClientAuthorization,
+        polling_sleep: &str,
         expected_config_data: Option<&PendingAction>,
         confirmation: Option<&PendingAction>,
         deployment: Option<&PendingAction>,
@@ -193,7 +238,7 @@ impl Target {
         let response = json!({
             "config": {
                 "polling": {
-                    "sleep": "00:01:00"
+                    "sleep": polling_sleep
                 }
             },
             "_links": links
@@ -228,6 +273,7 @@ impl Target {
             &self.tenant,
             &self.name,
             &self.client_auth,
+            &self.polling_sleep.borrow(),
             self.config_data.borrow().as_ref(),
             self.confirmation.borrow().as_ref(),
             self.deployment.borrow().as_ref(),
@@ -238,6 +284,12 @@ impl Target {
         old.delete();
     }
 
+    /// Change the polling sleep suggested to the target, formatted as `HH:MM:SS`.
+    pub fn set_polling_sleep(&self, polling_sleep: &str) {
+        self.polling_sleep.replace(polling_sleep.to_string());
+        self.update_poll();
+    }
+
     /// Request the target to upload its configuration to the server.
     /// One can then use [`Target::config_data_hits`] to check that the client
     /// uploaded its configuration and that it matches the one passed as `expected_config_data`.
@@ -405,7 +457,7 @@ impl Target {
 
             // Serve the artifacts
             for chunk in deploy.chunks.iter() {
-                for (artifact, _md5, _sha1, _sha256) in chunk.artifacts.iter() {
+                for (artifact, md5, _sha1, _sha256) in chunk.artifacts.iter() {
                     let file_name = artifact.file_name().unwrap().to_str().unwrap();
                     let path = format!("/download/{}", file_name);
 
@@ -413,22 +465,15 @@ impl Target {
                         self.server.mock(mock_fn);
                     } else {
                         self.server.mock(|when, then| {
-                            let when = when.method(GET).path(path);
-
-                            match &self.client_auth {
-                                ClientAuthorization::None => { /* do not require Authorization header */
-                                }
-                                ClientAuthorization::TargetToken(key) => {
-                                    when.header("Authorization", format!("TargetToken {}", key));
-                                }
-                                ClientAuthorization::GatewayToken(key) => {
-                                    when.header("Authorization", format!("GatewayToken {}", key));
-                                }
-                            };
-
+                            self.authorized(when.method(GET).path(&path));
                             then.status(200).body_from_file(artifact.to_str().unwrap());
                         });
                     }
+
+                    self.server.mock(|when, then| {
+                        self.authorized(when.method(GET).path(format!("{}.MD5SUM", path)));
+                        then.status(200).body(format!("{}  {}", md5, file_name));
+                    });
                 }
             }
 
@@ -778,6 +823,7 @@ pub struct DeploymentBuilder {
     update_type: Type,
     maintenance_window: Option<MaintenanceWindow>,
     chunks: Vec<Chunk>,
+    sizes: HashMap<String, u64>,
 }
 
 /// A pending deployment update pushed to the target.
@@ -789,6 +835,7 @@ pub struct Deployment {
     update_type: Type,
     maintenance_window: Option<MaintenanceWindow>,
     chunks: Vec<Chunk>,
+    sizes: HashMap<String, u64>,
 }
 
 impl DeploymentBuilder {
@@ -801,6 +848,7 @@ impl DeploymentBuilder {
             update_type,
             maintenance_window: None,
             chunks: Vec::new(),
+            sizes: HashMap::new(),
         }
     }
 
@@ -818,6 +866,16 @@ impl DeploymentBuilder {
         builder
     }
 
+    /// Announce `size` as the size of the artifacts named `file_name` instead of the size of the local file.
+    ///
+    /// This can be used to test artifacts too large to be stored by the test suite,
+    /// or downloads whose size does not match the announced one.
+    pub fn artifact_size(self, file_name: &str, size: u64) -> Self {
+        let mut builder = self;
+        builder.sizes.insert(file_name.to_string(), size);
+        builder
+    }
+
     /// Add a new software chunk to the deployment.
     /// # Arguments
     /// * `protocol`: The protocols over which chunks are downloadable
@@ -968,6 +1026,7 @@ impl DeploymentBuilder {
             update_type: self.update_type,
             maintenance_window: self.maintenance_window,
             chunks: self.chunks,
+            sizes: self.sizes,
         }
     }
 }
@@ -1021,7 +1080,7 @@ pub struct Chunk {
 }
 
 impl Chunk {
-    fn json(&self, base_url: &str) -> serde_json::Value {
+    fn json(&self, base_url: &str, sizes: &HashMap<String, u64>) -> serde_json::Value {
         let artifacts: Vec<serde_json::Value> = self
             .artifacts
             .iter()
@@ -1029,7 +1088,6 @@ impl Chunk {
                 let meta = path.metadata().unwrap();
                 let file_name = path.file_name().unwrap().to_str().unwrap();
                 let download_url = format!("{}/{}", base_url, file_name);
-                // TODO: the md5 url is not served by the http server
                 let md5_url = format!("{}.MD5SUM", download_url);
 
                 let mut links = serde_json::Map::new();
@@ -1050,7 +1108,7 @@ impl Chunk {
                         "md5": md5,
                         "sha256": sha256,
                     },
-                    "size": meta.len(),
+                    "size": sizes.get(file_name).copied().unwrap_or(meta.len()),
                     "_links": links,
                 })
             })
@@ -1077,7 +1135,11 @@ impl Chunk {
 
 impl Deployment {
     fn json(&self, base_url: &str) -> serde_json::Value {
-        let chunks: Vec<serde_json::Value> = self.chunks.iter().map(|c| c.json(base_url)).collect();
+        let chunks: Vec<serde_json::Value> = self
+            .chunks
+            .iter()
+            .map(|c| c.json(base_url, &self.sizes))
+            .collect();
 
         let mut j = if self.confirmation_required {
             json!({